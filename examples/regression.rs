@@ -0,0 +1,45 @@
+// fits y = 2x + 1 from noisy samples with a one-layer MLP trained by SGD;
+// examples/sine.rs covers a hidden layer on a nonlinear target
+use regrad::{mse, no_grad, Activation, DataLoader, Module, Rng, Tensor, MLP, SGD};
+
+const SAMPLES: usize = 64;
+const EPOCHS: usize = 100;
+
+pub fn main() {
+    let mut rng = Rng::new(1);
+
+    let xs: Vec<f64> = (0..SAMPLES).map(|_| rng.uniform(-1.0, 1.0)).collect();
+    let ys: Vec<f64> = xs.iter().map(|x| 2.0 * x + 1.0 + rng.normal(0.0, 0.1)).collect();
+    let features = Tensor::from_slice(&xs, vec![SAMPLES, 1]);
+    let labels = Tensor::from_slice(&ys, vec![SAMPLES, 1]);
+
+    // seeded, like the data, so every run (and the integration test) is identical
+    let model = MLP::with_rng(&[1, 1], Activation::Tanh, &mut rng);
+    let mut optimizer = SGD::new(model.parameters(), 0.1);
+
+    for epoch in 0..EPOCHS {
+        let loader = DataLoader::with_shuffle(&features, &labels, 16, &mut rng);
+        let batches = loader.num_batches() as f64;
+
+        let mut total = 0.0;
+        for (x, y) in loader {
+            optimizer.zero_grad();
+            let loss = mse(&model.forward(&x), &y);
+            loss.backward();
+            optimizer.step();
+            total += loss.data();
+        }
+
+        if epoch % 10 == 0 {
+            println!("epoch {:3}  loss {:.6}", epoch, total / batches);
+        }
+    }
+
+    let _guard = no_grad();
+    let at = |x: f64| model.forward(&Tensor::from_slice(&[x], vec![1, 1])).into_scalar().data();
+    let (slope, intercept) = (at(1.0) - at(0.0), at(0.0));
+    println!("learned y = {:.4}x + {:.4}", slope, intercept);
+
+    assert!((slope - 2.0).abs() < 0.1, "learned slope {} is not close to 2", slope);
+    assert!((intercept - 1.0).abs() < 0.1, "learned intercept {} is not close to 1", intercept);
+}
@@ -0,0 +1,46 @@
+// fits y = sin(2x) from noisy samples with a tanh MLP trained by SGD
+use regrad::{mse, no_grad, Activation, DataLoader, Module, Rng, Tensor, MLP, SGD};
+
+const SAMPLES: usize = 64;
+const EPOCHS: usize = 300;
+
+pub fn main() {
+    let mut rng = Rng::new(1);
+
+    let xs: Vec<f64> = (0..SAMPLES).map(|_| rng.uniform(-1.0, 1.0)).collect();
+    let ys: Vec<f64> = xs.iter().map(|x| (2.0 * x).sin() + rng.normal(0.0, 0.05)).collect();
+    let features = Tensor::from_slice(&xs, vec![SAMPLES, 1]);
+    let labels = Tensor::from_slice(&ys, vec![SAMPLES, 1]);
+
+    // the hidden layer is what lets the model bend; a single layer stays linear
+    let model = MLP::with_rng(&[1, 8, 1], Activation::Tanh, &mut rng);
+    let mut optimizer = SGD::with_momentum(model.parameters(), 0.1, 0.9, 0.0);
+
+    for epoch in 0..EPOCHS {
+        let loader = DataLoader::with_shuffle(&features, &labels, 16, &mut rng);
+        let batches = loader.num_batches() as f64;
+
+        let mut total = 0.0;
+        for (x, y) in loader {
+            optimizer.zero_grad();
+            let loss = mse(&model.forward(&x), &y);
+            loss.backward();
+            optimizer.step();
+            total += loss.data();
+        }
+
+        if epoch % 30 == 0 {
+            println!("epoch {:3}  loss {:.6}", epoch, total / batches);
+        }
+    }
+
+    // prediction error on a noise-free grid the model was not trained on
+    let _guard = no_grad();
+    let grid: Vec<f64> = (0..=40).map(|i| -1.0 + i as f64 * 0.05).collect();
+    let predictions = model.forward(&Tensor::from_slice(&grid, vec![grid.len(), 1]));
+    let targets = Tensor::from_slice(&grid.iter().map(|x| (2.0 * x).sin()).collect::<Vec<f64>>(), vec![grid.len(), 1]);
+    let error = mse(&predictions, &targets).data();
+    println!("test mse {:.6}", error);
+
+    assert!(error < 0.01, "test mse {} is not below 0.01", error);
+}
@@ -0,0 +1,135 @@
+use crate::{Rng, Tensor, Value};
+
+// splits features and labels along their first dimension into minibatches for
+// one epoch; construct a new loader (from the same rng) for every epoch
+pub struct DataLoader {
+    features: Vec<Value>,
+    feature_shape: Vec<usize>,
+    labels: Vec<Value>,
+    label_shape: Vec<usize>,
+    batch_size: usize,
+    order: Vec<usize>,
+    position: usize,
+}
+
+impl DataLoader {
+    pub fn new(features: &Tensor, labels: &Tensor, batch_size: usize) -> DataLoader {
+        let (feature_shape, label_shape) = (features.shape(), labels.shape());
+        assert_eq!(
+            feature_shape[0], label_shape[0],
+            "features {:?} and labels {:?} have different numbers of samples",
+            feature_shape, label_shape
+        );
+        assert!(batch_size > 0, "batch size must be positive");
+
+        DataLoader {
+            features: features.data(),
+            labels: labels.data(),
+            order: (0..feature_shape[0]).collect(),
+            feature_shape,
+            label_shape,
+            batch_size,
+            position: 0,
+        }
+    }
+
+    // samples are visited in a Fisher-Yates permutation drawn from rng
+    pub fn with_shuffle(features: &Tensor, labels: &Tensor, batch_size: usize, rng: &mut Rng) -> DataLoader {
+        let mut loader = DataLoader::new(features, labels, batch_size);
+
+        for i in (1..loader.order.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            loader.order.swap(i, j);
+        }
+
+        loader
+    }
+
+    // the total for the epoch, including batches already yielded
+    pub fn num_batches(&self) -> usize {
+        self.order.len().div_ceil(self.batch_size)
+    }
+}
+
+// the batch shares the original values, so gradients still reach the inputs
+fn gather(data: &[Value], shape: &[usize], samples: &[usize]) -> Tensor {
+    let row: usize = shape[1..].iter().product();
+    let data = samples
+        .iter()
+        .flat_map(|&sample| data[sample * row..(sample + 1) * row].iter().cloned())
+        .collect();
+
+    let mut shape = shape.to_vec();
+    shape[0] = samples.len();
+
+    Tensor::new(data, shape)
+}
+
+impl Iterator for DataLoader {
+    type Item = (Tensor, Tensor);
+
+    // the last batch holds whatever samples remain
+    fn next(&mut self) -> Option<(Tensor, Tensor)> {
+        if self.position >= self.order.len() {
+            return None;
+        }
+
+        let end = (self.position + self.batch_size).min(self.order.len());
+        let samples = &self.order[self.position..end];
+        self.position = end;
+
+        Some((
+            gather(&self.features, &self.feature_shape, samples),
+            gather(&self.labels, &self.label_shape, samples),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset() -> (Tensor, Tensor) {
+        let features = Tensor::from_slice(&(0..20).map(|x| x as f64).collect::<Vec<f64>>(), vec![10, 2]);
+        let labels = Tensor::from_slice(&(0..10).map(|x| x as f64).collect::<Vec<f64>>(), vec![10]);
+
+        (features, labels)
+    }
+
+    #[test]
+    fn test_data_loader_batches() {
+        let (features, labels) = dataset();
+
+        let loader = DataLoader::new(&features, &labels, 4);
+        assert_eq!(loader.num_batches(), 3);
+
+        let batches: Vec<(Tensor, Tensor)> = loader.collect();
+
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches.iter().map(|(x, _)| x.shape()).collect::<Vec<_>>(), vec![vec![4, 2], vec![4, 2], vec![2, 2]]);
+        assert_eq!(batches.iter().map(|(_, y)| y.shape()).collect::<Vec<_>>(), vec![vec![4], vec![4], vec![2]]);
+        assert_eq!(batches[2].0.to_vec(), vec![16.0, 17.0, 18.0, 19.0]);
+        assert_eq!(batches[2].1.to_vec(), vec![8.0, 9.0]);
+    }
+
+    #[test]
+    fn test_data_loader_shuffle() {
+        let (features, labels) = dataset();
+
+        let loader = DataLoader::with_shuffle(&features, &labels, 4, &mut Rng::new(3));
+        let again = DataLoader::with_shuffle(&features, &labels, 4, &mut Rng::new(3));
+
+        let mut seen = vec![];
+        for ((x, y), (x_again, _)) in loader.zip(again) {
+            assert_eq!(x.to_vec(), x_again.to_vec());
+            for (row, label) in x.to_vec().chunks(2).zip(y.to_vec()) {
+                assert_eq!(row, [2.0 * label, 2.0 * label + 1.0]);
+                seen.push(label);
+            }
+        }
+
+        assert_ne!(seen, (0..10).map(|x| x as f64).collect::<Vec<f64>>());
+        seen.sort_by(f64::total_cmp);
+        assert_eq!(seen, (0..10).map(|x| x as f64).collect::<Vec<f64>>());
+    }
+}
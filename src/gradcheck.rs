@@ -0,0 +1,153 @@
+use crate::{no_grad, Value};
+
+// compares the autodiff gradient of f at inputs against central differences
+// and returns the absolute discrepancy for every input
+pub fn grad_check(f: impl Fn(&[Value]) -> Value, inputs: &[Value], eps: f64) -> Vec<f64> {
+    for input in inputs {
+        input.zero_grad();
+    }
+    f(inputs).backward();
+    let analytic: Vec<f64> = inputs.iter().map(|input| input.gradient()).collect();
+
+    let _guard = no_grad();
+    inputs
+        .iter()
+        .zip(analytic)
+        .map(|(input, analytic)| {
+            let data = input.data();
+
+            input.set_data(data + eps);
+            let plus = f(inputs).data();
+            input.set_data(data - eps);
+            let minus = f(inputs).data();
+            input.set_data(data);
+
+            ((plus - minus) / (2.0 * eps) - analytic).abs()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_discrepancy(f: impl Fn(&[Value]) -> Value, inputs: &[f64]) -> f64 {
+        let inputs: Vec<Value> = inputs.iter().map(|&x| Value::from(x)).collect();
+
+        grad_check(f, &inputs, 1e-6).into_iter().fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_grad_check_relu() {
+        let f = |x: &[Value]| (&x[0] * &x[1]).relu();
+
+        assert!(max_discrepancy(f, &[1.5, 2.0]) < 1e-6);
+        assert!(max_discrepancy(f, &[-1.5, 2.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_tanh() {
+        let f = |x: &[Value]| (&x[0] + &(&x[0] * &x[1])).tanh();
+
+        assert!(max_discrepancy(f, &[0.3, -0.7]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_pow() {
+        let f = |x: &[Value]| &x[0].powf(3.0) + &x[0].powv(&x[1]);
+
+        assert!(max_discrepancy(f, &[1.3, 2.5]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_sqrt() {
+        let f = |x: &[Value]| x[0].sqrt();
+
+        assert!(max_discrepancy(f, &[0.7]) < 1e-6);
+        assert!(max_discrepancy(f, &[9.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_recip() {
+        let f = |x: &[Value]| x[0].recip();
+
+        assert!(max_discrepancy(f, &[1.5]) < 1e-6);
+        assert!(max_discrepancy(f, &[-0.8]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_exp2() {
+        let f = |x: &[Value]| x[0].exp2();
+
+        assert!(max_discrepancy(f, &[0.3]) < 1e-6);
+        assert!(max_discrepancy(f, &[-2.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_sin_cos() {
+        let f = |x: &[Value]| &x[0].sin() * &x[1].cos();
+
+        assert!(max_discrepancy(f, &[0.0, 0.0]) < 1e-6);
+        assert!(max_discrepancy(f, &[1.2, -2.7]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_softplus() {
+        let f = |x: &[Value]| x[0].softplus();
+
+        assert!(max_discrepancy(f, &[0.4]) < 1e-6);
+        assert!(max_discrepancy(f, &[-3.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_gelu() {
+        let f = |x: &[Value]| x[0].gelu();
+
+        for &x in &[-2.5, -0.5, 0.0, 0.8, 3.0] {
+            assert!(max_discrepancy(f, &[x]) < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_grad_check_elu() {
+        let f = |x: &[Value]| x[0].elu(1.5);
+
+        assert!(max_discrepancy(f, &[1.2]) < 1e-6);
+        assert!(max_discrepancy(f, &[-0.7]) < 1e-6);
+        assert!(max_discrepancy(f, &[-4.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_elu_second_derivative() {
+        for &(input, expected) in &[(-0.7, 1.5 * (-0.7_f64).exp()), (1.2, 0.0)] {
+            let x = Value::from(input);
+            let y = x.elu(1.5);
+
+            y.backward_create_graph();
+            let dy_dx = x.gradient_value();
+            x.zero_grad();
+            dy_dx.backward();
+
+            assert!((x.gradient() - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_grad_check_silu() {
+        let f = |x: &[Value]| x[0].silu();
+
+        assert!(max_discrepancy(f, &[1.2]) < 1e-6);
+        assert!(max_discrepancy(f, &[-0.7]) < 1e-6);
+        assert!(max_discrepancy(f, &[-4.0]) < 1e-6);
+    }
+
+    #[test]
+    fn test_grad_check_restores_inputs() {
+        let inputs = vec![Value::from(0.5)];
+
+        grad_check(|x: &[Value]| x[0].exp(), &inputs, 1e-6);
+
+        assert_eq!(inputs[0].data(), 0.5);
+        assert!(crate::is_grad_enabled());
+    }
+}
@@ -2,4 +2,22 @@ mod value;
 pub use value::*;
 
 mod tensor;
-pub use tensor::*;
\ No newline at end of file
+pub use tensor::*;
+
+mod rng;
+pub use rng::*;
+
+mod nn;
+pub use nn::*;
+
+mod optim;
+pub use optim::*;
+
+mod loss;
+pub use loss::*;
+
+mod gradcheck;
+pub use gradcheck::*;
+
+mod data;
+pub use data::*;
@@ -0,0 +1,154 @@
+use crate::{Tensor, Value};
+
+pub fn mse(pred: &Tensor, target: &Tensor) -> Value {
+    assert_eq!(
+        pred.shape(),
+        target.shape(),
+        "mse expects matching shapes, got {:?} and {:?}",
+        pred.shape(),
+        target.shape()
+    );
+
+    let diff = pred - target;
+
+    (&diff * &diff).mean().into_scalar()
+}
+
+// the negative log-probability of target, given 1-D log-probabilities such as
+// the output of Tensor::log_softmax
+pub fn nll_loss(log_probs: &Tensor, target: usize) -> Value {
+    assert_eq!(
+        log_probs.shape().len(),
+        1,
+        "nll_loss expects 1-D log-probabilities, got shape {:?}",
+        log_probs.shape()
+    );
+    assert!(
+        target < log_probs.size(),
+        "target index {} out of range for {} classes",
+        target,
+        log_probs.size()
+    );
+
+    -&log_probs.get(&[target])
+}
+
+// log-softmax followed by negative log-likelihood over a 1-D tensor of logits;
+// the largest logit is subtracted first so exp can never overflow
+pub fn cross_entropy(logits: &Tensor, target_index: usize) -> Value {
+    assert_eq!(
+        logits.shape().len(),
+        1,
+        "cross_entropy expects 1-D logits, got shape {:?}",
+        logits.shape()
+    );
+    assert!(
+        target_index < logits.size(),
+        "target index {} out of range for {} classes",
+        target_index,
+        logits.size()
+    );
+
+    let data = logits.data();
+    let max = Value::constant(data.iter().map(|v| v.data()).fold(f64::NEG_INFINITY, f64::max));
+
+    let sum_exp: Value = data.iter().map(|v| (v - &max).exp()).sum();
+    let log_sum_exp = &sum_exp.ln() + &max;
+
+    &log_sum_exp - &data[target_index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mse() {
+        let pred = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+        let target = Tensor::new(vec![Value::from(2.0), Value::from(4.0)], vec![2]);
+
+        let loss = mse(&pred, &target);
+        loss.backward();
+
+        assert_eq!(loss.data(), 2.5);
+        assert_eq!(pred.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![-1.0, -2.0]);
+    }
+
+    #[test]
+    fn test_mse_identical() {
+        let pred = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+        let target = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+
+        let loss = mse(&pred, &target);
+        loss.backward();
+
+        assert_eq!(loss.data(), 0.0);
+        assert_eq!(pred.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "matching shapes")]
+    fn test_mse_shape_mismatch() {
+        mse(&Tensor::zeros(vec![2]), &Tensor::zeros(vec![3]));
+    }
+
+    #[test]
+    fn test_mse_detached_target() {
+        let x = Tensor::from(vec![1.0, 2.0]);
+        let pred = &x * 2.0;
+        let target = &x + 1.0;
+
+        let loss = mse(&pred, &target.detach());
+        loss.backward();
+
+        assert_eq!(loss.data(), 0.5);
+        assert_eq!(target.gradient().to_vec(), vec![0.0, 0.0]);
+        assert_eq!(pred.gradient().to_vec(), vec![0.0, 1.0]);
+        assert_eq!(x.gradient().to_vec(), vec![0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let logits = Tensor::new(vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)], vec![3]);
+
+        let loss = cross_entropy(&logits, 1);
+        loss.backward();
+
+        let sum_exp: f64 = [1.0_f64, 2.0, 3.0].iter().map(|x| x.exp()).sum();
+        assert!((loss.data() - (sum_exp.ln() - 2.0)).abs() < 1e-12);
+
+        let gradient = logits.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>();
+        assert!(gradient[0] > 0.0);
+        assert!(gradient[1] < 0.0);
+        assert!(gradient[2] > 0.0);
+        assert!(gradient.iter().sum::<f64>().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cross_entropy_large_logits() {
+        let logits = Tensor::new(vec![Value::from(1000.0), Value::from(0.0)], vec![2]);
+
+        let loss = cross_entropy(&logits, 0);
+        loss.backward();
+
+        assert!(loss.data().abs() < 1e-12);
+        assert!(logits.gradient().data().iter().all(|v| v.data().is_finite()));
+    }
+
+    #[test]
+    fn test_nll_loss() {
+        let logits = Tensor::new(vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)], vec![3]);
+        let log_probs = logits.log_softmax(0);
+
+        let loss = nll_loss(&log_probs, 1);
+
+        assert_eq!(loss.data(), -log_probs.get(&[1]).data());
+        assert!((loss.data() - cross_entropy(&logits, 1).data()).abs() < 1e-12);
+
+        loss.backward();
+
+        let gradient = logits.gradient().to_vec();
+        assert!(gradient[1] < 0.0);
+        assert!(gradient.iter().sum::<f64>().abs() < 1e-12);
+    }
+}
@@ -0,0 +1,598 @@
+use std::cell::RefCell;
+
+use crate::{Rng, Tensor, Value};
+
+pub trait Module {
+    fn forward(&self, input: &Tensor) -> Tensor;
+
+    fn parameters(&self) -> Vec<Value>;
+
+    // names default to each parameter's position in parameters()
+    fn named_parameters(&self) -> Vec<(String, Value)> {
+        self.parameters()
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (i.to_string(), p))
+            .collect()
+    }
+}
+
+// a named value that starts out trainable; set_requires_grad(false) on it
+// freezes it, and the optimizers then leave it untouched. it builds its own
+// leaf, labelled with the name, rather than adopting a caller's value
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    name: String,
+    value: Value,
+}
+
+impl Parameter {
+    pub fn new(name: &str, data: f64) -> Parameter {
+        let value = Value::leaf(data, name);
+        value.set_requires_grad(true);
+
+        Parameter {
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn requires_grad(&self) -> bool {
+        self.value.requires_grad()
+    }
+
+    pub fn set_requires_grad(&self, requires_grad: bool) -> &Parameter {
+        self.value.set_requires_grad(requires_grad);
+        self
+    }
+}
+
+impl From<Parameter> for Value {
+    fn from(parameter: Parameter) -> Value {
+        parameter.value
+    }
+}
+
+pub struct Linear {
+    weight: Tensor,
+    bias: Tensor,
+}
+
+impl Linear {
+    // weights and bias are drawn from U(-1/sqrt(in), 1/sqrt(in)), as in PyTorch
+    pub fn new(in_features: usize, out_features: usize) -> Linear {
+        Linear::with_rng(in_features, out_features, &mut Rng::from_entropy())
+    }
+
+    // like new, but drawing from rng so that initialization is reproducible
+    pub fn with_rng(in_features: usize, out_features: usize, rng: &mut Rng) -> Linear {
+        let bound = 1.0 / (in_features as f64).sqrt();
+
+        let weight = (0..in_features * out_features)
+            .map(|_| Value::from(rng.uniform(-bound, bound)))
+            .collect();
+        let bias = (0..out_features)
+            .map(|_| Value::from(rng.uniform(-bound, bound)))
+            .collect();
+
+        Linear {
+            weight: Tensor::new(weight, vec![in_features, out_features]),
+            bias: Tensor::new(bias, vec![out_features]),
+        }
+    }
+
+    pub fn weight(&self) -> &Tensor {
+        &self.weight
+    }
+
+    pub fn bias(&self) -> &Tensor {
+        &self.bias
+    }
+}
+
+impl Module for Linear {
+    // input is [batch, in_features], output is [batch, out_features]
+    fn forward(&self, input: &Tensor) -> Tensor {
+        &input.matmul(&self.weight) + &self.bias
+    }
+
+    fn parameters(&self) -> Vec<Value> {
+        let mut parameters = self.weight.data();
+        parameters.extend(self.bias.data());
+
+        parameters
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Value)> {
+        let weight = self.weight.data().into_iter().enumerate().map(|(i, p)| (format!("weight.{}", i), p));
+        let bias = self.bias.data().into_iter().enumerate().map(|(i, p)| (format!("bias.{}", i), p));
+
+        weight.chain(bias).collect()
+    }
+}
+
+pub struct Embedding {
+    weight: Tensor,
+}
+
+impl Embedding {
+    // rows are drawn from N(0, 1), as in PyTorch
+    pub fn new(num_embeddings: usize, dim: usize) -> Embedding {
+        let mut rng = Rng::from_entropy();
+
+        Embedding {
+            weight: Tensor::randn(vec![num_embeddings, dim], 0.0, 1.0, &mut rng),
+        }
+    }
+
+    pub fn weight(&self) -> &Tensor {
+        &self.weight
+    }
+
+    // output is [indices.len(), dim]; repeated indices share the same row
+    // values, so their gradients accumulate in that row
+    pub fn forward(&self, indices: &[usize]) -> Tensor {
+        let (shape, weight) = (self.weight.shape(), self.weight.data());
+
+        let data = indices
+            .iter()
+            .flat_map(|&index| {
+                assert!(index < shape[0], "index {} out of range for {} embeddings", index, shape[0]);
+                weight[index * shape[1]..(index + 1) * shape[1]].to_vec()
+            })
+            .collect();
+
+        Tensor::new(data, vec![indices.len(), shape[1]])
+    }
+
+    pub fn parameters(&self) -> Vec<Value> {
+        self.weight.data()
+    }
+}
+
+pub struct BatchNorm1d {
+    gamma: Tensor,
+    beta: Tensor,
+    eps: f64,
+    momentum: f64,
+    running_mean: RefCell<Vec<f64>>,
+    running_var: RefCell<Vec<f64>>,
+}
+
+impl BatchNorm1d {
+    // eps and momentum default to 1e-5 and 0.1, as in PyTorch
+    pub fn new(num_features: usize) -> BatchNorm1d {
+        BatchNorm1d {
+            gamma: Tensor::ones(vec![num_features]),
+            beta: Tensor::zeros(vec![num_features]),
+            eps: 1e-5,
+            momentum: 0.1,
+            running_mean: RefCell::new(vec![0.0; num_features]),
+            running_var: RefCell::new(vec![1.0; num_features]),
+        }
+    }
+
+    pub fn gamma(&self) -> &Tensor {
+        &self.gamma
+    }
+
+    pub fn beta(&self) -> &Tensor {
+        &self.beta
+    }
+
+    pub fn running_mean(&self) -> Vec<f64> {
+        self.running_mean.borrow().clone()
+    }
+
+    pub fn running_var(&self) -> Vec<f64> {
+        self.running_var.borrow().clone()
+    }
+
+    // input is [batch, num_features]. training normalizes with the biased batch
+    // variance and folds the unbiased one into the running stats, as in PyTorch;
+    // evaluation normalizes with the running stats as constants
+    pub fn forward(&self, input: &Tensor, training: bool) -> Tensor {
+        let (shape, features) = (input.shape(), self.gamma.size());
+        assert!(
+            shape.len() == 2 && shape[1] == features,
+            "expected input of shape [batch, {}], got {:?}",
+            features,
+            shape
+        );
+
+        let (centered, var) = if training {
+            let mean = input.mean_axis(0);
+            let centered = input - &mean;
+            let var = centered.powf(2.0).mean_axis(0);
+
+            let correction = if shape[0] > 1 { shape[0] as f64 / (shape[0] - 1) as f64 } else { 1.0 };
+            let (mut running_mean, mut running_var) = (self.running_mean.borrow_mut(), self.running_var.borrow_mut());
+            for (running, batch) in running_mean.iter_mut().zip(mean.to_vec()) {
+                *running = (1.0 - self.momentum) * *running + self.momentum * batch;
+            }
+            for (running, batch) in running_var.iter_mut().zip(var.to_vec()) {
+                *running = (1.0 - self.momentum) * *running + self.momentum * batch * correction;
+            }
+
+            (centered, var)
+        } else {
+            let mean = Tensor::from_slice(&self.running_mean.borrow(), vec![features]);
+            let var = Tensor::from_slice(&self.running_var.borrow(), vec![features]);
+
+            (input - &mean, var)
+        };
+
+        let normalized = &centered * &(&var + self.eps).powf(-0.5);
+
+        &(&normalized * &self.gamma) + &self.beta
+    }
+
+    pub fn parameters(&self) -> Vec<Value> {
+        let mut parameters = self.gamma.data();
+        parameters.extend(self.beta.data());
+
+        parameters
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation {
+    Relu,
+    Tanh,
+    Sigmoid,
+    Identity,
+    // the slope applied to negative inputs
+    LeakyRelu(f64),
+}
+
+impl Activation {
+    pub fn apply(&self, input: &Tensor) -> Tensor {
+        input.activate(*self)
+    }
+}
+
+// lets an activation sit between layers in a Sequential
+impl Module for Activation {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        self.apply(input)
+    }
+
+    fn parameters(&self) -> Vec<Value> {
+        vec![]
+    }
+}
+
+#[derive(Default)]
+pub struct Sequential {
+    modules: Vec<Box<dyn Module>>,
+}
+
+impl Sequential {
+    pub fn new() -> Sequential {
+        Sequential { modules: vec![] }
+    }
+
+    pub fn push(mut self, module: impl Module + 'static) -> Sequential {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    pub fn modules(&self) -> &[Box<dyn Module>] {
+        &self.modules
+    }
+}
+
+impl Module for Sequential {
+    fn forward(&self, input: &Tensor) -> Tensor {
+        // an empty Sequential passes the input's values through in new storage,
+        // like the Identity activation
+        match self.modules.split_first() {
+            Some((first, rest)) => rest.iter().fold(first.forward(input), |output, module| module.forward(&output)),
+            None => Tensor::new(input.data(), input.shape()),
+        }
+    }
+
+    fn parameters(&self) -> Vec<Value> {
+        self.modules.iter().flat_map(|module| module.parameters()).collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Value)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, module)| {
+                module
+                    .named_parameters()
+                    .into_iter()
+                    .map(move |(name, p)| (format!("{}.{}", i, name), p))
+            })
+            .collect()
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct MLP {
+    layers: Vec<Linear>,
+    activation: Activation,
+}
+
+impl MLP {
+    // sizes lists the input width followed by every layer's output width
+    pub fn new(sizes: &[usize], activation: Activation) -> MLP {
+        MLP::with_rng(sizes, activation, &mut Rng::from_entropy())
+    }
+
+    pub fn with_rng(sizes: &[usize], activation: Activation, rng: &mut Rng) -> MLP {
+        assert!(sizes.len() >= 2, "an MLP needs at least an input and an output size, got {:?}", sizes);
+
+        let layers = sizes.windows(2).map(|pair| Linear::with_rng(pair[0], pair[1], rng)).collect();
+
+        MLP { layers, activation }
+    }
+
+    pub fn layers(&self) -> &[Linear] {
+        &self.layers
+    }
+}
+
+impl Module for MLP {
+    // the activation is applied between layers, so the output is left linear
+    fn forward(&self, input: &Tensor) -> Tensor {
+        let mut output = self.layers[0].forward(input);
+        for layer in &self.layers[1..] {
+            output = layer.forward(&self.activation.apply(&output));
+        }
+
+        output
+    }
+
+    fn parameters(&self) -> Vec<Value> {
+        self.layers.iter().flat_map(|layer| layer.parameters()).collect()
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Value)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(l, layer)| {
+                layer
+                    .named_parameters()
+                    .into_iter()
+                    .map(move |(name, p)| (format!("layers.{}.{}", l, name), p))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_forward() {
+        let linear = Linear::new(3, 2);
+        let input = Tensor::new(vec![Value::from(1.0), Value::from(-2.0), Value::from(3.0)], vec![1, 3]);
+
+        let output = linear.forward(&input);
+
+        assert_eq!(output.shape(), vec![1, 2]);
+        assert_eq!(linear.weight().shape(), vec![3, 2]);
+        assert_eq!(linear.bias().shape(), vec![2]);
+
+        output.sum().backward();
+
+        assert_eq!(linear.parameters().len(), 8);
+        for p in linear.parameters() {
+            assert_ne!(p.gradient(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_seeded_init_is_reproducible() {
+        let first = MLP::with_rng(&[2, 3, 1], Activation::Tanh, &mut Rng::new(7));
+        let second = MLP::with_rng(&[2, 3, 1], Activation::Tanh, &mut Rng::new(7));
+
+        let data = |mlp: &MLP| mlp.parameters().iter().map(|p| p.data()).collect::<Vec<f64>>();
+
+        assert_eq!(data(&first), data(&second));
+    }
+
+    #[test]
+    fn test_linear_init_bounds() {
+        let linear = Linear::new(4, 3);
+
+        for p in linear.parameters() {
+            assert!(p.data().abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn test_mlp_forward() {
+        let mlp = MLP::new(&[2, 4, 1], Activation::Tanh);
+        let input = Tensor::new(vec![Value::from(0.5), Value::from(-1.0)], vec![1, 2]);
+
+        let output = mlp.forward(&input);
+
+        assert_eq!(output.shape(), vec![1, 1]);
+        assert_eq!(mlp.layers().len(), 2);
+        assert_eq!(mlp.parameters().len(), (2 * 4 + 4) + (4 + 1));
+
+        output.sum().backward();
+
+        assert!(mlp.parameters().iter().any(|p| p.gradient() != 0.0));
+    }
+
+    #[test]
+    fn test_mlp_activation_between_layers() {
+        let mlp = MLP::new(&[3, 2], Activation::Relu);
+        let input = Tensor::new(vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)], vec![1, 3]);
+
+        // a single layer has no hidden activation, so it matches Linear exactly
+        let expected = mlp.layers()[0].forward(&input);
+
+        assert_eq!(mlp.forward(&input).to_vec(), expected.to_vec());
+    }
+
+    #[test]
+    fn test_module_sequence() {
+        let modules: Vec<Box<dyn Module>> = vec![
+            Box::new(Linear::new(3, 4)),
+            Box::new(MLP::new(&[4, 5, 2], Activation::Relu)),
+            Box::new(Linear::new(2, 1)),
+        ];
+        let input = Tensor::new(vec![Value::from(0.1), Value::from(0.2), Value::from(0.3)], vec![1, 3]);
+
+        let output = modules.iter().fold(input, |x, module| module.forward(&x));
+        let parameters: Vec<Value> = modules.iter().flat_map(|module| module.parameters()).collect();
+
+        assert_eq!(output.shape(), vec![1, 1]);
+        assert_eq!(parameters.len(), (3 * 4 + 4) + (4 * 5 + 5) + (5 * 2 + 2) + (2 + 1));
+    }
+
+    #[test]
+    fn test_embedding_lookup() {
+        let embedding = Embedding::new(3, 2);
+
+        let output = embedding.forward(&[0, 2, 0]);
+
+        assert_eq!(output.shape(), vec![3, 2]);
+        assert_eq!(output.get(&[1, 1]), embedding.weight().get(&[2, 1]));
+        assert_eq!(embedding.parameters().len(), 6);
+
+        output.sum().backward();
+
+        assert_eq!(embedding.weight().gradient().to_vec(), vec![2.0, 2.0, 0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_embedding_out_of_range() {
+        Embedding::new(3, 2).forward(&[3]);
+    }
+
+    #[test]
+    fn test_batch_norm_training() {
+        let bn = BatchNorm1d::new(2);
+        let input = Tensor::from(vec![vec![1.0, 10.0], vec![2.0, 20.0], vec![3.0, 30.0], vec![4.0, 40.0]]);
+
+        let output = bn.forward(&input, true);
+
+        assert_eq!(output.shape(), vec![4, 2]);
+        for mean in output.mean_axis(0).to_vec() {
+            assert!(mean.abs() < 1e-9);
+        }
+        for var in output.powf(2.0).mean_axis(0).to_vec() {
+            assert!((var - 1.0).abs() < 1e-3);
+        }
+
+        output.sum().backward();
+
+        assert_eq!(bn.parameters().len(), 4);
+        assert_eq!(bn.beta().gradient().to_vec(), vec![4.0, 4.0]);
+    }
+
+    #[test]
+    fn test_batch_norm_running_stats() {
+        let bn = BatchNorm1d::new(1);
+        let input = Tensor::from(vec![vec![4.0], vec![6.0]]);
+
+        bn.forward(&input, true);
+
+        assert!((bn.running_mean()[0] - 0.5).abs() < 1e-12);
+        assert!((bn.running_var()[0] - 1.1).abs() < 1e-12);
+
+        let mut previous = bn.running_mean()[0];
+        for _ in 0..20 {
+            bn.forward(&input, true);
+            assert!(bn.running_mean()[0] > previous && bn.running_mean()[0] < 5.0);
+            previous = bn.running_mean()[0];
+        }
+        assert!((bn.running_var()[0] - 2.0).abs() < 0.2);
+
+        // eval mode only reads the running stats
+        let before = bn.running_mean();
+        let output = bn.forward(&Tensor::from(vec![vec![previous]]), false);
+
+        assert!(output.to_vec()[0].abs() < 1e-9);
+        assert_eq!(bn.running_mean(), before);
+    }
+
+    #[test]
+    fn test_named_parameters() {
+        let mlp = MLP::new(&[2, 3, 1], Activation::Relu);
+
+        let named = mlp.named_parameters();
+        let names: Vec<&str> = named.iter().map(|(name, _)| name.as_str()).collect();
+
+        assert_eq!(named.len(), mlp.parameters().len());
+        assert_eq!(names[0], "layers.0.weight.0");
+        assert_eq!(names[6], "layers.0.bias.0");
+        assert_eq!(names[names.len() - 1], "layers.1.bias.0");
+        assert_eq!(named[6].1, mlp.layers()[0].bias().get(&[0]));
+    }
+
+    #[test]
+    fn test_frozen_parameter_skipped() {
+        let trained = Parameter::new("trained", 1.0);
+        let frozen = Parameter::new("frozen", 1.0);
+        frozen.set_requires_grad(false);
+
+        assert_eq!(frozen.name(), "frozen");
+        assert_eq!(frozen.value().label(), Some("frozen".to_string()));
+        assert!(trained.requires_grad());
+        assert!(!frozen.requires_grad());
+
+        let loss = &(trained.value() * frozen.value()) + &(frozen.value() * frozen.value());
+        let parameters = vec![trained.clone(), frozen.clone()];
+        let mut optimizer = crate::SGD::with_momentum(parameters.into_iter().map(Value::from).collect(), 0.1, 0.9, 0.1);
+
+        loss.backward();
+        optimizer.step();
+
+        assert_eq!(trained.value().data(), 1.0 - 0.1 * (1.0 + 0.1));
+        assert_eq!(frozen.value().data(), 1.0);
+    }
+
+    #[test]
+    fn test_sequential() {
+        let model = Sequential::new()
+            .push(Linear::new(3, 4))
+            .push(Activation::Tanh)
+            .push(Linear::new(4, 2));
+        let input = Tensor::from(vec![vec![0.1, 0.2, 0.3], vec![-0.3, 0.2, -0.1]]);
+
+        let output = model.forward(&input);
+
+        assert_eq!(output.shape(), vec![2, 2]);
+        assert_eq!(model.modules().len(), 3);
+        assert_eq!(model.parameters().len(), (3 * 4 + 4) + (4 * 2 + 2));
+        assert_eq!(model.named_parameters()[16].0, "2.weight.0");
+
+        output.sum().backward();
+
+        for module in model.modules() {
+            for p in module.parameters() {
+                assert_ne!(p.gradient(), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_sequential_is_identity() {
+        let input = Tensor::from(vec![1.0, 2.0]);
+
+        let output = Sequential::new().forward(&input);
+        output.set(&[0], Value::from(7.0));
+
+        assert_eq!(output.data()[1..], input.data()[1..]);
+        assert_eq!(input.to_vec(), vec![1.0, 2.0]);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::Value;
+
+pub trait LrScheduler {
+    fn lr(&self, epoch: usize, base_lr: f64) -> f64;
+}
+
+// multiplies the rate by gamma every step_size epochs
+#[derive(Clone, Copy, Debug)]
+pub struct StepLR {
+    step_size: usize,
+    gamma: f64,
+}
+
+impl StepLR {
+    pub fn new(step_size: usize, gamma: f64) -> StepLR {
+        assert!(step_size > 0, "StepLR step_size must be positive");
+
+        StepLR { step_size, gamma }
+    }
+
+    pub fn step_size(&self) -> usize {
+        self.step_size
+    }
+
+    pub fn gamma(&self) -> f64 {
+        self.gamma
+    }
+}
+
+impl LrScheduler for StepLR {
+    fn lr(&self, epoch: usize, base_lr: f64) -> f64 {
+        base_lr * self.gamma.powi((epoch / self.step_size) as i32)
+    }
+}
+
+// follows half a cosine from base_lr down to eta_min over t_max epochs, then
+// stays at eta_min
+#[derive(Clone, Copy, Debug)]
+pub struct CosineAnnealing {
+    t_max: usize,
+    eta_min: f64,
+}
+
+impl CosineAnnealing {
+    pub fn new(t_max: usize, eta_min: f64) -> CosineAnnealing {
+        assert!(t_max > 0, "CosineAnnealing t_max must be positive");
+
+        CosineAnnealing { t_max, eta_min }
+    }
+
+    pub fn t_max(&self) -> usize {
+        self.t_max
+    }
+
+    pub fn eta_min(&self) -> f64 {
+        self.eta_min
+    }
+}
+
+impl LrScheduler for CosineAnnealing {
+    fn lr(&self, epoch: usize, base_lr: f64) -> f64 {
+        let progress = epoch.min(self.t_max) as f64 / self.t_max as f64;
+
+        self.eta_min + (base_lr - self.eta_min) * (1.0 + (std::f64::consts::PI * progress).cos()) / 2.0
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct SGD {
+    params: Vec<Value>,
+    lr: f64,
+    momentum: f64,
+    weight_decay: f64,
+    velocity: Vec<f64>,
+    scheduler: Option<Box<dyn LrScheduler>>,
+    epoch: usize,
+}
+
+impl SGD {
+    pub fn new(params: Vec<Value>, lr: f64) -> SGD {
+        SGD::with_momentum(params, lr, 0.0, 0.0)
+    }
+
+    pub fn with_momentum(params: Vec<Value>, lr: f64, momentum: f64, weight_decay: f64) -> SGD {
+        let velocity = vec![0.0; params.len()];
+
+        SGD {
+            params,
+            lr,
+            momentum,
+            weight_decay,
+            velocity,
+            scheduler: None,
+            epoch: 0,
+        }
+    }
+
+    // the rate for the current epoch, after the scheduler (if any) is applied
+    pub fn lr(&self) -> f64 {
+        match &self.scheduler {
+            Some(scheduler) => scheduler.lr(self.epoch, self.lr),
+            None => self.lr,
+        }
+    }
+
+    pub fn set_scheduler(&mut self, scheduler: impl LrScheduler + 'static) {
+        self.scheduler = Some(Box::new(scheduler));
+    }
+
+    pub fn next_epoch(&mut self) {
+        self.epoch += 1;
+    }
+
+    // v = momentum * v - lr * (gradient + weight_decay * data), then data += v;
+    // frozen parameters (requires_grad false) are skipped
+    pub fn step(&mut self) {
+        let lr = self.lr();
+        for (p, v) in self.params.iter().zip(self.velocity.iter_mut()) {
+            if !p.requires_grad() {
+                continue;
+            }
+
+            let data = p.data();
+            *v = self.momentum * *v - lr * (p.gradient() + self.weight_decay * data);
+            p.set_data(data + *v);
+        }
+    }
+
+    pub fn zero_grad(&self) {
+        for p in self.params.iter() {
+            p.zero_grad();
+        }
+    }
+}
+
+pub struct Adam {
+    params: Vec<Value>,
+    lr: f64,
+    beta1: f64,
+    beta2: f64,
+    eps: f64,
+    t: i32,
+    m: Vec<f64>,
+    v: Vec<f64>,
+    scheduler: Option<Box<dyn LrScheduler>>,
+    epoch: usize,
+}
+
+impl Adam {
+    pub fn new(params: Vec<Value>, lr: f64) -> Adam {
+        Adam::with_betas(params, lr, 0.9, 0.999, 1e-8)
+    }
+
+    pub fn with_betas(params: Vec<Value>, lr: f64, beta1: f64, beta2: f64, eps: f64) -> Adam {
+        let m = vec![0.0; params.len()];
+        let v = vec![0.0; params.len()];
+
+        Adam {
+            params,
+            lr,
+            beta1,
+            beta2,
+            eps,
+            t: 0,
+            m,
+            v,
+            scheduler: None,
+            epoch: 0,
+        }
+    }
+
+    pub fn lr(&self) -> f64 {
+        match &self.scheduler {
+            Some(scheduler) => scheduler.lr(self.epoch, self.lr),
+            None => self.lr,
+        }
+    }
+
+    pub fn set_scheduler(&mut self, scheduler: impl LrScheduler + 'static) {
+        self.scheduler = Some(Box::new(scheduler));
+    }
+
+    pub fn next_epoch(&mut self) {
+        self.epoch += 1;
+    }
+
+    pub fn step(&mut self) {
+        let lr = self.lr();
+        self.t += 1;
+        let m_correction = 1.0 - self.beta1.powi(self.t);
+        let v_correction = 1.0 - self.beta2.powi(self.t);
+
+        for ((p, m), v) in self.params.iter().zip(self.m.iter_mut()).zip(self.v.iter_mut()) {
+            if !p.requires_grad() {
+                continue;
+            }
+
+            let gradient = p.gradient();
+            *m = self.beta1 * *m + (1.0 - self.beta1) * gradient;
+            *v = self.beta2 * *v + (1.0 - self.beta2) * gradient * gradient;
+
+            let m_hat = *m / m_correction;
+            let v_hat = *v / v_correction;
+            p.set_data(p.data() - lr * m_hat / (v_hat.sqrt() + self.eps));
+        }
+    }
+
+    pub fn zero_grad(&mut self) {
+        for p in self.params.iter() {
+            p.zero_grad();
+        }
+    }
+}
+
+// rescales the gradients in place so their joint L2 norm is at most max_norm,
+// returning the norm from before clipping
+pub fn clip_grad_norm(params: &[Value], max_norm: f64) -> f64 {
+    let norm = params.iter().map(|p| p.gradient() * p.gradient()).sum::<f64>().sqrt();
+
+    if norm > max_norm {
+        let scale = max_norm / norm;
+        for p in params {
+            p.set_gradient(p.gradient() * scale);
+        }
+    }
+
+    norm
+}
+
+// lambda * sum(p^2), to be added to the loss before backward; each parameter
+// then receives an extra 2 * lambda * p
+pub fn l2_penalty(params: &[Value], lambda: f64) -> Value {
+    &Value::constant(lambda) * &params.iter().map(|p| p * p).sum()
+}
+
+// lambda * sum(|p|), which pushes parameters towards exactly zero
+pub fn l1_penalty(params: &[Value], lambda: f64) -> Value {
+    &Value::constant(lambda) * &params.iter().map(|p| p.abs()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimize(mut optimizer: SGD, x: &Value, steps: usize) {
+        for _ in 0..steps {
+            optimizer.zero_grad();
+            let loss = (x - &Value::from(3.0)).powf(2.0);
+            loss.backward();
+            optimizer.step();
+        }
+    }
+
+    #[test]
+    fn test_sgd_quadratic() {
+        let x = Value::from(0.0);
+
+        minimize(SGD::new(vec![x.clone()], 0.1), &x, 100);
+
+        assert!((x.data() - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sgd_step_descends() {
+        let x = Value::from(1.0);
+        let mut optimizer = SGD::new(vec![x.clone()], 0.5);
+
+        (&x * &x).backward();
+        optimizer.step();
+
+        assert_eq!(x.data(), 0.0);
+    }
+
+    #[test]
+    fn test_sgd_momentum_accelerates() {
+        let plain = Value::from(0.0);
+        let accelerated = Value::from(0.0);
+
+        minimize(SGD::new(vec![plain.clone()], 0.01), &plain, 30);
+        minimize(SGD::with_momentum(vec![accelerated.clone()], 0.01, 0.9, 0.0), &accelerated, 30);
+
+        assert!((accelerated.data() - 3.0).abs() < (plain.data() - 3.0).abs());
+    }
+
+    #[test]
+    fn test_sgd_weight_decay() {
+        let x = Value::from(2.0);
+        let mut optimizer = SGD::with_momentum(vec![x.clone()], 0.1, 0.0, 0.5);
+
+        // with a zero gradient only the decay term moves the parameter
+        optimizer.step();
+
+        assert!((x.data() - 1.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_adam_linear_regression() {
+        let w = Value::from(0.0);
+        let b = Value::from(0.0);
+        let mut optimizer = Adam::new(vec![w.clone(), b.clone()], 0.05);
+        let samples = [(-1.0, -1.0), (0.0, 1.0), (1.0, 3.0), (2.0, 5.0)];
+
+        let mut losses = vec![];
+        for _ in 0..50 {
+            optimizer.zero_grad();
+            let loss = samples
+                .iter()
+                .map(|&(x, y)| (&(&w * &Value::from(x)) + &b - Value::from(y)).powf(2.0))
+                .reduce(|acc, l| &acc + &l)
+                .unwrap();
+            loss.backward();
+            optimizer.step();
+            losses.push(loss.data());
+        }
+
+        assert!(losses.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn test_clip_grad_norm_scales_down() {
+        let x = Value::from(3.0);
+        let y = Value::from(4.0);
+        (&(&x * &x) + &(&y * &y)).backward();
+
+        let norm = clip_grad_norm(&[x.clone(), y.clone()], 1.0);
+
+        assert!((norm - 10.0).abs() < 1e-12);
+        assert!((x.gradient() - 0.6).abs() < 1e-12);
+        assert!((y.gradient() - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_clip_grad_norm_small_untouched() {
+        let x = Value::from(0.1);
+        (&x * &x).backward();
+
+        let norm = clip_grad_norm(std::slice::from_ref(&x), 1.0);
+
+        assert_eq!(norm, 0.2);
+        assert_eq!(x.gradient(), 0.2);
+    }
+
+    #[test]
+    fn test_l2_penalty() {
+        let params = vec![Value::from(1.5), Value::from(-2.0)];
+
+        let penalty = l2_penalty(&params, 0.1);
+        penalty.backward();
+
+        assert!((penalty.data() - 0.1 * 6.25).abs() < 1e-12);
+        assert!((params[0].gradient() - 2.0 * 0.1 * 1.5).abs() < 1e-12);
+        assert!((params[1].gradient() - 2.0 * 0.1 * -2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_l1_penalty() {
+        let params = vec![Value::from(1.5), Value::from(-2.0), Value::from(0.0)];
+
+        let penalty = l1_penalty(&params, 0.5);
+        penalty.backward();
+
+        assert_eq!(penalty.data(), 1.75);
+        assert_eq!(params.iter().map(|p| p.gradient()).collect::<Vec<f64>>(), vec![0.5, -0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_step_lr() {
+        let scheduler = StepLR::new(3, 0.5);
+
+        assert_eq!((scheduler.step_size(), scheduler.gamma()), (3, 0.5));
+
+        let rates: Vec<f64> = (0..7).map(|epoch| scheduler.lr(epoch, 0.8)).collect();
+
+        assert_eq!(rates, vec![0.8, 0.8, 0.8, 0.4, 0.4, 0.4, 0.2]);
+    }
+
+    #[test]
+    fn test_cosine_annealing() {
+        let scheduler = CosineAnnealing::new(10, 0.01);
+
+        assert_eq!((scheduler.t_max(), scheduler.eta_min()), (10, 0.01));
+
+        assert_eq!(scheduler.lr(0, 0.1), 0.1);
+        assert!((scheduler.lr(5, 0.1) - 0.055).abs() < 1e-12);
+        assert!((scheduler.lr(10, 0.1) - 0.01).abs() < 1e-12);
+        assert!((scheduler.lr(15, 0.1) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_optimizer_scheduler() {
+        let x = Value::from(1.0);
+        let mut optimizer = SGD::new(vec![x.clone()], 0.5);
+        optimizer.set_scheduler(StepLR::new(1, 0.5));
+
+        optimizer.next_epoch();
+        (&x * &x).backward();
+        optimizer.step();
+
+        assert_eq!(optimizer.lr(), 0.25);
+        assert_eq!(x.data(), 0.5);
+
+        let mut adam = Adam::new(vec![x.clone()], 0.1);
+        adam.set_scheduler(CosineAnnealing::new(2, 0.0));
+        adam.next_epoch();
+        adam.next_epoch();
+
+        assert_eq!(adam.lr(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "step_size must be positive")]
+    fn test_step_lr_zero_step_size() {
+        StepLR::new(0, 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "t_max must be positive")]
+    fn test_cosine_annealing_zero_t_max() {
+        CosineAnnealing::new(0, 0.0);
+    }
+}
@@ -1,9 +1,17 @@
 // implemented from:
 // https://towardsdatascience.com/recreating-pytorch-from-scratch-with-gpu-support-and-automatic-differentiation-8f565122a3cc
 
-use std::{cell::RefCell, hash::Hash, ops::{Add, Deref, Mul, Neg, Sub}, rc::Rc};
-
-use crate::Value;
+use std::{
+    cell::RefCell,
+    fs,
+    hash::Hash,
+    io::{self, Read, Write},
+    ops::{Add, Deref, Mul, Neg, Sub},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{Activation, Rng, Value};
 
 pub struct Tensor {
     internal: Rc<RefCell<TensorInternal>>,
@@ -21,13 +29,30 @@ impl Tensor {
         }
     }
 
+    pub fn from_slice(data: &[f64], shape: Vec<usize>) -> Tensor {
+        assert_eq!(
+            data.len(),
+            shape.iter().product::<usize>(),
+            "{} elements cannot fill shape {:?}",
+            data.len(),
+            shape
+        );
+
+        Tensor::new(data.iter().map(|&x| Value::from(x)).collect(), shape)
+    }
+
+    pub fn scalar(v: Value) -> Tensor {
+        Tensor::new(vec![v], vec![1])
+    }
+
+    // every element is a distinct node; cloning one Value would share it
     pub fn zeros(shape: Vec<usize>) -> Tensor {
         let size = shape.iter().product();
         let strides = compute_strides(shape.clone());
 
         Tensor {
             internal: Rc::new(RefCell::new(TensorInternal::new(
-                vec![Value::from(0.0); size],
+                (0..size).map(|_| Value::from(0.0)).collect(),
                 shape,
                 strides,
                 size,
@@ -41,7 +66,7 @@ impl Tensor {
 
         Tensor {
             internal: Rc::new(RefCell::new(TensorInternal::new(
-                vec![Value::from(1.0); size],
+                (0..size).map(|_| Value::from(1.0)).collect(),
                 shape,
                 strides,
                 size,
@@ -49,8 +74,59 @@ impl Tensor {
         }
     }
 
+    pub fn randn(shape: Vec<usize>, mean: f64, std: f64, rng: &mut Rng) -> Tensor {
+        let size = shape.iter().product();
+        let data = (0..size).map(|_| Value::from(rng.normal(mean, std))).collect();
+
+        Tensor::new(data, shape)
+    }
+
+    pub fn rand_uniform(shape: Vec<usize>, low: f64, high: f64, rng: &mut Rng) -> Tensor {
+        let size = shape.iter().product();
+        let data = (0..size).map(|_| Value::from(rng.uniform(low, high))).collect();
+
+        Tensor::new(data, shape)
+    }
+
+    // weights are laid out [in, out] as in Linear, so fan_in is the first dimension
+    pub fn xavier(shape: Vec<usize>, rng: &mut Rng) -> Tensor {
+        let (fan_in, fan_out) = fans(&shape);
+        let bound = (6.0 / (fan_in + fan_out) as f64).sqrt();
+
+        Tensor::rand_uniform(shape, -bound, bound, rng)
+    }
+
+    pub fn kaiming(shape: Vec<usize>, rng: &mut Rng) -> Tensor {
+        let (fan_in, _) = fans(&shape);
+        let std = (2.0 / fan_in as f64).sqrt();
+
+        Tensor::randn(shape, 0.0, std, rng)
+    }
+
     pub fn data(&self) -> Vec<Value> {
-        self.internal.borrow().data.clone()
+        self.internal.borrow().data.borrow().clone()
+    }
+
+    pub fn to_vec(&self) -> Vec<f64> {
+        self.internal.borrow().data.borrow().iter().map(|v| v.data()).collect()
+    }
+
+    // the single element itself, not a copy, so it stays connected to the graph
+    pub fn into_scalar(&self) -> Value {
+        assert_eq!(
+            self.size(),
+            1,
+            "into_scalar expects a tensor with a single element, got shape {:?}",
+            self.shape()
+        );
+
+        self.internal.borrow().data.borrow()[0].clone()
+    }
+
+    // compares data only, so tensors built from distinct nodes can still match
+    pub fn all_close(&self, other: &Tensor, tol: f64) -> bool {
+        self.shape() == other.shape()
+            && self.to_vec().iter().zip(other.to_vec()).all(|(u, v)| (u - v).abs() <= tol)
     }
 
     pub fn shape(&self) -> Vec<usize> {
@@ -65,13 +141,437 @@ impl Tensor {
         self.internal.borrow().size
     }
 
+    pub fn get(&self, index: &[usize]) -> Value {
+        let offset = self.offset(index);
+
+        self.internal.borrow().data.borrow()[offset].clone()
+    }
+
+    // replaces the element in place, so the tensor (and every view of it) now
+    // refers to value's node
+    pub fn set(&self, index: &[usize], value: Value) {
+        let offset = self.offset(index);
+
+        self.internal.borrow().data.borrow_mut()[offset] = value;
+    }
+
+    fn offset(&self, index: &[usize]) -> usize {
+        let (shape, strides) = (self.shape(), self.strides());
+        assert_eq!(
+            index.len(),
+            shape.len(),
+            "index {:?} does not match the rank of shape {:?}",
+            index,
+            shape
+        );
+
+        index.iter().zip(shape.iter()).zip(strides.iter()).fold(0, |acc, ((&i, &dim), &stride)| {
+            assert!(i < dim, "index {:?} out of range for shape {:?}", index, shape);
+            acc + i * stride
+        })
+    }
+
     pub fn reshape(&self, shape: Vec<usize>) -> &Tensor {
-        assert_eq!(self.size(), shape.iter().product());
-        self.internal.borrow_mut().shape = shape;
+        assert_eq!(
+            self.size(),
+            shape.iter().product::<usize>(),
+            "cannot reshape {:?} into {:?}",
+            self.shape(),
+            shape
+        );
+
+        let mut internal = self.internal.borrow_mut();
+        internal.strides = compute_strides(shape.clone());
+        internal.shape = shape;
+        drop(internal);
 
         self
     }
 
+    // unlike reshape, leaves self untouched and returns a new tensor with its own
+    // shape over the same elements, so set on either is visible through both
+    pub fn view(&self, shape: Vec<usize>) -> Tensor {
+        assert_eq!(
+            self.size(),
+            shape.iter().product::<usize>(),
+            "cannot view {:?} as {:?}",
+            self.shape(),
+            shape
+        );
+
+        let internal = TensorInternal {
+            data: Rc::clone(&self.internal.borrow().data),
+            strides: compute_strides(shape.clone()),
+            size: self.size(),
+            shape,
+        };
+
+        Tensor {
+            internal: Rc::new(RefCell::new(internal)),
+        }
+    }
+
+    // each slice along the first axis, holding the same values as self
+    pub fn rows(&self) -> impl Iterator<Item = Tensor> {
+        let (data, shape) = (self.data(), self.shape());
+        assert!(!shape.is_empty(), "rows expects a tensor of rank at least 1, got shape {:?}", shape);
+
+        let row_shape = shape[1..].to_vec();
+        let row_size = row_shape.iter().product::<usize>();
+
+        (0..shape[0]).map(move |i| Tensor::new(data[i * row_size..(i + 1) * row_size].to_vec(), row_shape.clone()))
+    }
+
+    // pads[d] is the number of zeros before and after axis d. the zeros are
+    // constants, so gradients only reach the original elements in the interior
+    pub fn pad(&self, pads: &[(usize, usize)]) -> Tensor {
+        let (data, shape, strides) = (self.data(), self.shape(), self.strides());
+        assert_eq!(
+            pads.len(),
+            shape.len(),
+            "pads {:?} do not match the rank of shape {:?}",
+            pads,
+            shape
+        );
+
+        let padded_shape: Vec<usize> = shape.iter().zip(pads).map(|(&dim, &(before, after))| before + dim + after).collect();
+        let padded_strides = compute_strides(padded_shape.clone());
+
+        let mut padded: Vec<Value> = (0..padded_shape.iter().product::<usize>()).map(|_| Value::constant(0.0)).collect();
+        for (i, value) in data.into_iter().enumerate() {
+            let offset = (0..shape.len())
+                .map(|d| ((i / strides[d]) % shape[d] + pads[d].0) * padded_strides[d])
+                .sum::<usize>();
+            padded[offset] = value;
+        }
+
+        Tensor::new(padded, padded_shape)
+    }
+
+    // consecutive chunks of the given sizes along axis, holding the same values
+    // as self so each chunk's gradients land on the elements it was cut from
+    pub fn split(&self, sizes: &[usize], axis: usize) -> Vec<Tensor> {
+        let (data, shape, strides) = (self.data(), self.shape(), self.strides());
+        assert!(axis < shape.len(), "axis {} out of range for shape {:?}", axis, shape);
+        assert_eq!(
+            sizes.iter().sum::<usize>(),
+            shape[axis],
+            "split sizes {:?} do not add up to axis {} of shape {:?}",
+            sizes,
+            axis,
+            shape
+        );
+
+        let mut start = 0;
+        sizes
+            .iter()
+            .map(|&size| {
+                let mut chunk_shape = shape.clone();
+                chunk_shape[axis] = size;
+                let chunk_strides = compute_strides(chunk_shape.clone());
+
+                let chunk = (0..chunk_shape.iter().product::<usize>())
+                    .map(|flat| {
+                        let offset = (0..shape.len())
+                            .map(|d| {
+                                let index = flat / chunk_strides[d] % chunk_shape[d];
+                                (if d == axis { index + start } else { index }) * strides[d]
+                            })
+                            .sum::<usize>();
+                        data[offset].clone()
+                    })
+                    .collect();
+                start += size;
+
+                Tensor::new(chunk, chunk_shape)
+            })
+            .collect()
+    }
+
+    pub fn flatten(&self) -> Tensor {
+        self.view(vec![self.size()])
+    }
+
+    pub fn squeeze(&self, axis: usize) -> Tensor {
+        let mut shape = self.shape();
+        assert!(axis < shape.len(), "axis {} out of range for shape {:?}", axis, shape);
+        assert_eq!(shape[axis], 1, "cannot squeeze axis {} of shape {:?}", axis, shape);
+
+        shape.remove(axis);
+        self.view(shape)
+    }
+
+    // axis may equal the rank, which appends the new axis at the end
+    pub fn unsqueeze(&self, axis: usize) -> Tensor {
+        let mut shape = self.shape();
+        assert!(axis <= shape.len(), "axis {} out of range for shape {:?}", axis, shape);
+
+        shape.insert(axis, 1);
+        self.view(shape)
+    }
+
+    // repeated positions share the original values, so their gradients sum back
+    // into the element they were expanded from
+    pub fn broadcast_to(&self, shape: Vec<usize>) -> Tensor {
+        let own = self.shape();
+        let compatible = own.len() <= shape.len()
+            && own.iter().rev().zip(shape.iter().rev()).all(|(&from, &to)| from == to || from == 1);
+        assert!(compatible, "shape {:?} cannot be broadcast to {:?}", own, shape);
+
+        Tensor::new(broadcast_data(self, &shape), shape)
+    }
+
+    pub fn sum(&self) -> Tensor {
+        Tensor::new(vec![self.data().iter().sum()], vec![1])
+    }
+
+    // the reduced dimension is removed; reducing a 1-D tensor gives shape [1]
+    pub fn sum_axis(&self, axis: usize) -> Tensor {
+        self.reduce_axis(axis, |slice| slice.into_iter().sum())
+    }
+
+    // the reverse of broadcast_to: sums away the leading axes shape lacks and
+    // every axis where shape has 1, so a broadcast gradient can be collapsed
+    // back onto the operand it came from
+    pub fn sum_to_shape(&self, shape: Vec<usize>) -> Tensor {
+        let own = self.shape();
+        let compatible = shape.len() <= own.len()
+            && own.iter().rev().zip(shape.iter().rev()).all(|(&from, &to)| from == to || to == 1);
+        assert!(compatible, "shape {:?} cannot be summed to {:?}", own, shape);
+
+        let mut reduced = self.view(own.clone());
+        for _ in 0..own.len() - shape.len() {
+            reduced = reduced.sum_axis(0);
+        }
+        for (axis, &dim) in shape.iter().enumerate() {
+            if dim == 1 && reduced.shape()[axis] != 1 {
+                reduced = reduced.sum_axis(axis).unsqueeze(axis);
+            }
+        }
+
+        reduced.view(shape)
+    }
+
+    // ties go to the first maximal element, which alone receives the gradient
+    pub fn max(&self) -> Value {
+        self.data().into_iter().reduce(|acc, v| acc.max(&v)).expect("max of an empty tensor")
+    }
+
+    pub fn argmax(&self) -> usize {
+        let data = self.data();
+        assert!(!data.is_empty(), "argmax of an empty tensor");
+
+        (1..data.len()).fold(0, |best, i| if data[i].data() > data[best].data() { i } else { best })
+    }
+
+    pub fn max_axis(&self, axis: usize) -> Tensor {
+        self.reduce_axis(axis, |slice| slice.into_iter().reduce(|acc, v| acc.max(&v)).unwrap())
+    }
+
+    fn reduce_axis(&self, axis: usize, reduce: impl Fn(Vec<Value>) -> Value) -> Tensor {
+        let (data, shape, strides) = (self.data(), self.shape(), self.strides());
+        assert!(axis < shape.len(), "axis {} out of range for shape {:?}", axis, shape);
+
+        let mut out_shape = shape.clone();
+        out_shape.remove(axis);
+        let out_strides = compute_strides(out_shape.clone());
+        let out_size = out_shape.iter().product();
+
+        let data = (0..out_size)
+            .map(|flat| {
+                let mut base = 0;
+                for (i, &dim) in out_shape.iter().enumerate() {
+                    let source_dim = if i < axis { i } else { i + 1 };
+                    base += flat / out_strides[i] % dim * strides[source_dim];
+                }
+
+                reduce((0..shape[axis]).map(|k| data[base + k * strides[axis]].clone()).collect())
+            })
+            .collect();
+
+        if out_shape.is_empty() {
+            out_shape.push(1);
+        }
+
+        Tensor::new(data, out_shape)
+    }
+
+    pub fn mean(&self) -> Tensor {
+        let count = Value::constant(self.size() as f64);
+
+        Tensor::scalar(&self.sum().into_scalar() / &count)
+    }
+
+    pub fn mean_axis(&self, axis: usize) -> Tensor {
+        let sum = self.sum_axis(axis);
+        let count = Value::constant(self.shape()[axis] as f64);
+        let data = sum.data().iter().map(|v| v / &count).collect();
+
+        Tensor::new(data, sum.shape())
+    }
+
+    pub fn relu(&self) -> Tensor {
+        self.map(Value::relu)
+    }
+
+    pub fn tanh(&self) -> Tensor {
+        self.map(Value::tanh)
+    }
+
+    pub fn sigmoid(&self) -> Tensor {
+        self.map(Value::sigmoid)
+    }
+
+    // Identity returns the same values in new storage, so setting an element
+    // of the result leaves self alone, as with every other activation
+    pub fn activate(&self, activation: Activation) -> Tensor {
+        match activation {
+            Activation::Relu => self.relu(),
+            Activation::Tanh => self.tanh(),
+            Activation::Sigmoid => self.sigmoid(),
+            Activation::Identity => Tensor::new(self.data(), self.shape()),
+            Activation::LeakyRelu(slope) => self.map(|v| v.leaky_relu(slope)),
+        }
+    }
+
+    pub fn powf(&self, n: f64) -> Tensor {
+        self.map(|v| v.powf(n))
+    }
+
+    pub fn exp(&self) -> Tensor {
+        self.map(Value::exp)
+    }
+
+    pub fn ln(&self) -> Tensor {
+        self.map(Value::ln)
+    }
+
+    // the max of each slice is subtracted as a constant before exponentiating;
+    // softmax is invariant to the shift, so gradients are unaffected
+    pub fn softmax(&self, axis: usize) -> Tensor {
+        self.map_axis(axis, |slice| {
+            let max = Value::constant(slice.iter().map(|v| v.data()).fold(f64::NEG_INFINITY, f64::max));
+            let exps: Vec<Value> = slice.iter().map(|v| (v - &max).exp()).collect();
+            let sum: Value = exps.iter().sum();
+
+            exps.iter().map(|e| e / &sum).collect()
+        })
+    }
+
+    // x - max - ln(sum(exp(x - max))) along axis, so exp never overflows and
+    // the result stays finite where softmax(..).ln() would underflow to -inf
+    pub fn log_softmax(&self, axis: usize) -> Tensor {
+        self.map_axis(axis, |slice| {
+            let max = Value::constant(slice.iter().map(|v| v.data()).fold(f64::NEG_INFINITY, f64::max));
+            let shifted: Vec<Value> = slice.iter().map(|v| v - &max).collect();
+            let log_sum_exp = shifted.iter().map(Value::exp).sum::<Value>().ln();
+
+            shifted.iter().map(|s| s - &log_sum_exp).collect()
+        })
+    }
+
+    // uses the biased variance, as in PyTorch, and has no learned scale or shift
+    pub fn layer_norm(&self, axis: usize, eps: f64) -> Tensor {
+        self.map_axis(axis, |slice| {
+            let count = Value::constant(slice.len() as f64);
+            let mean = &slice.iter().sum::<Value>() / &count;
+            let centered: Vec<Value> = slice.iter().map(|v| v - &mean).collect();
+            let variance = &centered.iter().map(|c| c * c).sum::<Value>() / &count;
+            let std = (&variance + &Value::constant(eps)).sqrt();
+
+            centered.iter().map(|c| c / &std).collect()
+        })
+    }
+
+    // inverted dropout: survivors are scaled by 1 / (1 - p) during training so
+    // that evaluation can use the input unchanged. dropped elements become
+    // constant zeros, so no gradient reaches them
+    pub fn dropout(&self, p: f64, training: bool, seed: u64) -> Tensor {
+        assert!((0.0..1.0).contains(&p), "dropout probability must be in [0, 1), got {}", p);
+
+        if !training {
+            return Tensor::new(self.data(), self.shape());
+        }
+
+        let mut rng = Rng::new(seed);
+        let scale = Value::constant(1.0 / (1.0 - p));
+        let data = self
+            .data()
+            .iter()
+            .map(|v| if rng.next_f64() < p { Value::constant(0.0) } else { v * &scale })
+            .collect();
+
+        Tensor::new(data, self.shape())
+    }
+
+    // applies f to every element, keeping the shape; f builds on the element
+    // with Value ops, so custom elementwise functions get gradients for free
+    pub fn map(&self, f: impl Fn(&Value) -> Value) -> Tensor {
+        Tensor::new(self.data().iter().map(f).collect(), self.shape())
+    }
+
+    // the binary counterpart of map; unlike the arithmetic operators it does
+    // not broadcast, so both tensors must have the same shape
+    pub fn zip_map(&self, other: &Tensor, f: impl Fn(&Value, &Value) -> Value) -> Tensor {
+        assert_eq!(
+            self.shape(),
+            other.shape(),
+            "zip_map expects matching shapes, got {:?} and {:?}",
+            self.shape(),
+            other.shape()
+        );
+
+        let data = self.data().iter().zip(other.data().iter()).map(|(u, v)| f(u, v)).collect();
+
+        Tensor::new(data, self.shape())
+    }
+
+    // replaces every 1-D slice along axis with f of that slice, keeping the shape
+    fn map_axis(&self, axis: usize, f: impl Fn(&[Value]) -> Vec<Value>) -> Tensor {
+        let (data, shape, strides) = (self.data(), self.shape(), self.strides());
+        assert!(axis < shape.len(), "axis {} out of range for shape {:?}", axis, shape);
+
+        let mut out = data.clone();
+        for base in (0..self.size()).filter(|flat| flat / strides[axis] % shape[axis] == 0) {
+            let indices: Vec<usize> = (0..shape[axis]).map(|k| base + k * strides[axis]).collect();
+            let slice: Vec<Value> = indices.iter().map(|&i| data[i].clone()).collect();
+
+            for (&i, v) in indices.iter().zip(f(&slice)) {
+                out[i] = v;
+            }
+        }
+
+        Tensor::new(out, shape)
+    }
+
+    // picks a where cond is nonzero and b elsewhere, broadcasting all three.
+    // the selected values are the original nodes, so each position's gradient
+    // reaches only the branch it was taken from; cond itself gets none
+    pub fn where_(cond: &Tensor, a: &Tensor, b: &Tensor) -> Tensor {
+        let shape = broadcast_shape(&broadcast_shape(&cond.shape(), &a.shape()), &b.shape());
+
+        let data = broadcast_data(cond, &shape)
+            .iter()
+            .zip(broadcast_data(a, &shape))
+            .zip(broadcast_data(b, &shape))
+            .map(|((c, a), b)| if c.data() != 0.0 { a } else { b })
+            .collect();
+
+        Tensor::new(data, shape)
+    }
+
+    // a copy of the current data with no graph behind it, so inference loops
+    // don't keep every intermediate alive; backward through it reaches nothing
+    pub fn forward_only(&self) -> Tensor {
+        self.detach()
+    }
+
+    // new leaves holding the same data, e.g. for loss targets that should not
+    // collect gradients or carry the graph that produced them
+    pub fn detach(&self) -> Tensor {
+        self.map(Value::detach)
+    }
+
     pub fn gradient(&self) -> Tensor {
         let data = self
             .data()
@@ -101,11 +601,225 @@ impl Tensor {
         }
     }
 
+    pub fn step(&self, lr: f64) {
+        for v in self.data() {
+            v.step(lr);
+        }
+    }
+
+    // adds other's data into the existing values without recording any graph,
+    // e.g. for gradient accumulators or running averages of weights
+    pub fn add_assign_data(&self, other: &Tensor) {
+        assert_eq!(
+            self.shape(),
+            other.shape(),
+            "add_assign_data expects matching shapes, got {:?} and {:?}",
+            self.shape(),
+            other.shape()
+        );
+
+        for (v, x) in self.data().iter().zip(other.to_vec()) {
+            v.set_data(v.data() + x);
+        }
+    }
+
+    // the result is laid out contiguously but shares the original values
+    pub fn transpose(&self) -> Tensor {
+        let (data, shape, strides) = (self.data(), self.shape(), self.strides());
+        assert_eq!(shape.len(), 2, "transpose expects a 2-D tensor, got shape {:?}", shape);
+
+        let (m, n) = (shape[0], shape[1]);
+        let (row, column) = (strides[0], strides[1]);
+        let data = (0..n)
+            .flat_map(|j| (0..m).map(move |i| i * row + j * column))
+            .map(|index| data[index].clone())
+            .collect();
+
+        Tensor::new(data, vec![n, m])
+    }
+
+    // 3-D inputs are a batch of matrices, [b, m, n] x [b, n, p] -> [b, m, p]
+    pub fn matmul(&self, other: &Tensor) -> Tensor {
+        let (mut shape, mut other_shape) = (self.shape(), other.shape());
+        assert!(
+            (shape.len() == 2 || shape.len() == 3) && shape.len() == other_shape.len(),
+            "matmul expects two 2-D or two 3-D tensors, got shapes {:?} and {:?}",
+            shape,
+            other_shape
+        );
+
+        // a 2-D operand is treated as a batch of one with a batch stride of 0
+        let batched = shape.len() == 3;
+        let (mut us, mut vs) = (self.strides(), other.strides());
+        if !batched {
+            shape.insert(0, 1);
+            other_shape.insert(0, 1);
+            us.insert(0, 0);
+            vs.insert(0, 0);
+        }
+
+        assert_eq!(
+            shape[0], other_shape[0],
+            "matmul batch size mismatch: {:?} x {:?}",
+            shape, other_shape
+        );
+        assert_eq!(
+            shape[2], other_shape[1],
+            "matmul shape mismatch: {:?} x {:?}",
+            shape, other_shape
+        );
+
+        let (b, m, n, p) = (shape[0], shape[1], shape[2], other_shape[2]);
+        let (u, v) = (self.data(), other.data());
+
+        let mut data = Vec::with_capacity(b * m * p);
+        for batch in 0..b {
+            let (ub, vb) = (batch * us[0], batch * vs[0]);
+            for i in 0..m {
+                for j in 0..p {
+                    // an empty inner dimension sums nothing, giving a constant zero
+                    let acc = (0..n)
+                        .map(|k| &u[ub + i * us[1] + k * us[2]] * &v[vb + k * vs[1] + j * vs[2]])
+                        .reduce(|acc, term| &acc + &term)
+                        .unwrap_or_else(|| Value::constant(0.0));
+                    data.push(acc);
+                }
+            }
+        }
+
+        Tensor::new(data, if batched { vec![b, m, p] } else { vec![m, p] })
+    }
+
+    pub fn outer(&self, other: &Tensor) -> Tensor {
+        let (shape, other_shape) = (self.shape(), other.shape());
+        assert!(
+            shape.len() == 1 && other_shape.len() == 1,
+            "outer expects two 1-D tensors, got shapes {:?} and {:?}",
+            shape,
+            other_shape
+        );
+
+        let (u, v) = (self.data(), other.data());
+        let data = u.iter().flat_map(|a| v.iter().map(move |b| a * b)).collect();
+
+        Tensor::new(data, vec![shape[0], other_shape[0]])
+    }
+
+    // single-channel cross-correlation; padded positions are zeros that are
+    // simply left out of the sum, so no gradient is recorded for them
+    pub fn conv1d(&self, kernel: &Tensor, stride: usize, padding: usize) -> Tensor {
+        let (shape, kernel_shape) = (self.shape(), kernel.shape());
+        assert!(
+            shape.len() == 1 && kernel_shape.len() == 1,
+            "conv1d expects a 1-D input and kernel, got shapes {:?} and {:?}",
+            shape,
+            kernel_shape
+        );
+        assert!(stride > 0, "conv1d stride must be positive");
+
+        let (n, k) = (shape[0], kernel_shape[0]);
+        assert!(
+            k <= n + 2 * padding,
+            "conv1d kernel of size {} is larger than the padded input of size {}",
+            k,
+            n + 2 * padding
+        );
+
+        let (x, w) = (self.data(), kernel.data());
+        let out = (n + 2 * padding - k) / stride + 1;
+
+        let data = (0..out)
+            .map(|i| {
+                (0..k)
+                    .filter_map(|j| {
+                        let pos = (i * stride + j).checked_sub(padding).filter(|&pos| pos < n)?;
+                        Some(&x[pos] * &w[j])
+                    })
+                    .reduce(|acc, term| &acc + &term)
+                    .unwrap_or_else(|| Value::constant(0.0))
+            })
+            .collect();
+
+        Tensor::new(data, vec![out])
+    }
+
+    // like PyTorch, the implicit gradient of 1.0 is only defined for scalar outputs
     pub fn backward(&self) {
-        unimplemented!("Tensor backward")
+        assert_eq!(
+            self.size(),
+            1,
+            "backward can only be called on a tensor with a single element, got shape {:?}",
+            self.shape()
+        );
+
+        self.internal.borrow().data.borrow()[0].backward();
+    }
+
+    // the magic bytes, the rank and each dimension as a u64, then the data as
+    // f64, all little-endian; gradients and the graph are not written
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let shape = self.shape();
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+
+        bytes.extend((shape.len() as u64).to_le_bytes());
+        for dim in shape {
+            bytes.extend((dim as u64).to_le_bytes());
+        }
+        for v in self.data() {
+            bytes.extend(v.data().to_le_bytes());
+        }
+
+        fs::File::create(path)?.write_all(&bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Tensor> {
+        let mut file = fs::File::open(path)?;
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if magic != CHECKPOINT_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a regrad tensor checkpoint"));
+        }
+
+        // the header is checked against the file length before anything is
+        // allocated, so a corrupt one cannot overflow or ask for huge buffers
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let remaining = file.metadata()?.len().saturating_sub(CHECKPOINT_MAGIC.len() as u64 + 8);
+
+        let mut word = [0; 8];
+        file.read_exact(&mut word)?;
+        let rank = u64::from_le_bytes(word);
+        if rank > remaining / 8 {
+            return Err(invalid("checkpoint rank does not fit in the file"));
+        }
+
+        let mut shape = vec![];
+        for _ in 0..rank {
+            file.read_exact(&mut word)?;
+            let dim = usize::try_from(u64::from_le_bytes(word)).map_err(|_| invalid("checkpoint dimension too large"))?;
+            shape.push(dim);
+        }
+
+        let size = shape
+            .iter()
+            .try_fold(1usize, |size, &dim| size.checked_mul(dim))
+            .ok_or_else(|| invalid("checkpoint shape overflows"))?;
+        if size as u64 > (remaining - rank * 8) / 8 {
+            return Err(invalid("checkpoint shape does not match the data in the file"));
+        }
+
+        let mut data = Vec::with_capacity(size);
+        for _ in 0..size {
+            file.read_exact(&mut word)?;
+            data.push(Value::from(f64::from_le_bytes(word)));
+        }
+
+        Ok(Tensor::new(data, shape))
     }
 }
 
+const CHECKPOINT_MAGIC: [u8; 4] = *b"RGTS";
+
 impl Hash for Tensor {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.internal.borrow().hash(state);
@@ -120,25 +834,42 @@ impl Deref for Tensor {
     }
 }
 
+impl From<Vec<f64>> for Tensor {
+    fn from(data: Vec<f64>) -> Self {
+        let shape = vec![data.len()];
+
+        Tensor::from_slice(&data, shape)
+    }
+}
+
+impl From<Vec<Vec<f64>>> for Tensor {
+    fn from(rows: Vec<Vec<f64>>) -> Self {
+        let columns = rows.first().map_or(0, |row| row.len());
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                columns,
+                "row {} has {} elements but the first row has {}",
+                i,
+                row.len(),
+                columns
+            );
+        }
+
+        Tensor::from_slice(&rows.concat(), vec![rows.len(), columns])
+    }
+}
+
 fn add(u: &Tensor, v: &Tensor) -> Tensor {
-    assert_eq!(u.shape(), v.shape());
+    let shape = broadcast_shape(&u.shape(), &v.shape());
 
-    let data = u
-        .data()
+    let data = broadcast_data(u, &shape)
         .iter()
-        .zip(v.data().iter())
+        .zip(broadcast_data(v, &shape).iter())
         .map(|(u, v)| u + v)
         .collect();
 
-    let shape = u.shape();
-    let size = u.size();
-    let strides = u.strides();
-
-    Tensor {
-        internal: Rc::new(RefCell::new(TensorInternal::new(
-            data, shape, strides, size,
-        ))),
-    }
+    Tensor::new(data, shape)
 }
 
 impl Add for Tensor {
@@ -149,7 +880,7 @@ impl Add for Tensor {
     }
 }
 
-impl<'a, 'b> Add<&'b Tensor> for &'a Tensor {
+impl<'b> Add<&'b Tensor> for &Tensor {
     type Output = Tensor;
 
     fn add(self, other: &'b Tensor) -> Tensor {
@@ -157,25 +888,33 @@ impl<'a, 'b> Add<&'b Tensor> for &'a Tensor {
     }
 }
 
+// the float becomes a shape [1] tensor, which broadcasts against any shape
+impl Add<f64> for Tensor {
+    type Output = Tensor;
+
+    fn add(self, other: f64) -> Tensor {
+        add(&self, &Tensor::scalar(Value::constant(other)))
+    }
+}
+
+impl Add<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn add(self, other: f64) -> Tensor {
+        add(self, &Tensor::scalar(Value::constant(other)))
+    }
+}
+
 fn mul(u: &Tensor, v: &Tensor) -> Tensor {
-    assert_eq!(u.shape(), v.shape());
+    let shape = broadcast_shape(&u.shape(), &v.shape());
 
-    let data = u
-        .data()
+    let data = broadcast_data(u, &shape)
         .iter()
-        .zip(v.data().iter())
+        .zip(broadcast_data(v, &shape).iter())
         .map(|(u, v)| u * v)
         .collect();
 
-    let shape = u.shape();
-    let size = u.size();
-    let strides = u.strides();
-
-    Tensor {
-        internal: Rc::new(RefCell::new(TensorInternal::new(
-            data, shape, strides, size,
-        ))),
-    }
+    Tensor::new(data, shape)
 }
 
 impl Mul<Tensor> for Tensor {
@@ -186,7 +925,7 @@ impl Mul<Tensor> for Tensor {
     }
 }
 
-impl<'a, 'b> Mul<&'b Tensor> for &'a Tensor {
+impl<'b> Mul<&'b Tensor> for &Tensor {
     type Output = Tensor;
 
     fn mul(self, other: &'b Tensor) -> Tensor {
@@ -212,35 +951,63 @@ impl<'a> Mul<&'a Value> for &Tensor {
     }
 }
 
+impl Mul<f64> for Tensor {
+    type Output = Tensor;
+
+    fn mul(self, other: f64) -> Tensor {
+        self * Value::constant(other)
+    }
+}
+
+impl Mul<f64> for &Tensor {
+    type Output = Tensor;
+
+    fn mul(self, other: f64) -> Tensor {
+        self * &Value::constant(other)
+    }
+}
+
 impl Neg for Tensor {
     type Output = Tensor;
 
     fn neg(self) -> Tensor {
-        self * Value::from(-1.0)
+        self.map(|v| -v)
     }
 }
 
-impl<'a> Neg for &'a Tensor {
+impl Neg for &Tensor {
     type Output = Tensor;
 
     fn neg(self) -> Tensor {
-        self * &Value::from(-1.0)
+        self.map(|v| -v)
     }
 }
 
+fn sub(u: &Tensor, v: &Tensor) -> Tensor {
+    let shape = broadcast_shape(&u.shape(), &v.shape());
+
+    let data = broadcast_data(u, &shape)
+        .iter()
+        .zip(broadcast_data(v, &shape).iter())
+        .map(|(u, v)| u - v)
+        .collect();
+
+    Tensor::new(data, shape)
+}
+
 impl Sub for Tensor {
     type Output = Tensor;
 
     fn sub(self, other: Tensor) -> Tensor {
-        self + (-other)
+        sub(&self, &other)
     }
 }
 
-impl<'a, 'b> Sub<&'b Tensor> for &'a Tensor {
+impl<'b> Sub<&'b Tensor> for &Tensor {
     type Output = Tensor;
 
     fn sub(self, other: &'b Tensor) -> Tensor {
-        self + &(-other)
+        sub(self, other)
     }
 }
 
@@ -279,28 +1046,1064 @@ mod tests {
     }
 
     #[test]
-    fn test_neg() {
-        let t1 = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+    fn test_scalar_f64_ops() {
+        let t = Tensor::from_slice(&[1.0, 2.0], vec![2]);
 
-        let t2 = -t1;
+        let scaled = &t * 3.0;
+        let shifted = &t + 1.0;
 
-        assert_eq!(t2.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![-1.0, -2.0]);
+        assert_eq!(scaled.to_vec(), vec![3.0, 6.0]);
+        assert_eq!(shifted.to_vec(), vec![2.0, 3.0]);
+
+        (&scaled + &shifted).sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![4.0, 4.0]);
     }
 
     #[test]
-    fn test_sub() {
-        let t1 = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
-        let t2 = Tensor::new(vec![Value::from(3.0), Value::from(4.0)], vec![2]);
+    fn test_add_assign_data() {
+        let accumulator = Tensor::zeros(vec![2, 2]);
+        let values = accumulator.data();
 
-        let t3 = &t1 - &t2;
+        accumulator.add_assign_data(&Tensor::from_slice(&[1.0, 2.0, 3.0, 4.0], vec![2, 2]));
+        accumulator.add_assign_data(&Tensor::from_slice(&[0.5, 0.5, -1.0, 0.0], vec![2, 2]));
 
-        assert_eq!(t3.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![-2.0, -2.0]);
+        assert_eq!(accumulator.to_vec(), vec![1.5, 2.5, 2.0, 4.0]);
+        assert_eq!(accumulator.data(), values);
     }
-}
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
-pub struct TensorInternal {
-    data: Vec<Value>,
+    #[test]
+    #[should_panic(expected = "matching shapes")]
+    fn test_add_assign_data_shape_mismatch() {
+        Tensor::zeros(vec![2]).add_assign_data(&Tensor::zeros(vec![3]));
+    }
+
+    #[test]
+    fn test_neg() {
+        let t1 = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+
+        let t2 = -t1;
+
+        assert_eq!(t2.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![-1.0, -2.0]);
+    }
+
+    #[test]
+    fn test_sub() {
+        let t1 = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+        let t2 = Tensor::new(vec![Value::from(3.0), Value::from(4.0)], vec![2]);
+
+        let t3 = &t1 - &t2;
+
+        assert_eq!(t3.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![-2.0, -2.0]);
+
+        // each element is a single Sub node over the original operands
+        let dot = t3.data()[0].to_dot();
+        assert_eq!(dot.matches("shape=oval").count(), 1);
+        assert!(dot.contains("label=\"Sub\""));
+
+        t3.sum().backward();
+
+        assert_eq!(t1.gradient().to_vec(), vec![1.0, 1.0]);
+        assert_eq!(t2.gradient().to_vec(), vec![-1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_backward() {
+        let a = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+        let b = Tensor::new(vec![Value::from(3.0), Value::from(4.0)], vec![2]);
+
+        let t = (&a * &b).sum();
+
+        t.backward();
+
+        assert_eq!(a.gradient().shape(), vec![2]);
+        assert_eq!(a.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![3.0, 4.0]);
+        assert_eq!(b.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "single element")]
+    fn test_backward_non_scalar() {
+        let t = Tensor::new(vec![Value::from(1.0), Value::from(2.0)], vec![2]);
+
+        t.backward();
+    }
+
+    #[test]
+    fn test_matmul() {
+        let a = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+        let b = Tensor::new((7..=12).map(|x| Value::from(x as f64)).collect(), vec![3, 2]);
+
+        let c = a.matmul(&b);
+
+        assert_eq!(c.shape(), vec![2, 2]);
+        assert_eq!(c.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![58.0, 64.0, 139.0, 154.0]);
+
+        c.sum().backward();
+
+        assert_eq!(a.gradient().shape(), vec![2, 3]);
+        assert_eq!(b.gradient().shape(), vec![3, 2]);
+        assert_eq!(
+            a.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![15.0, 19.0, 23.0, 15.0, 19.0, 23.0]
+        );
+        assert_eq!(
+            b.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![5.0, 5.0, 7.0, 7.0, 9.0, 9.0]
+        );
+    }
+
+    #[test]
+    fn test_rows() {
+        let t = Tensor::from(vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]]);
+
+        let rows: Vec<Tensor> = t.rows().collect();
+
+        assert_eq!(rows.len(), 3);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.shape(), vec![2]);
+            assert_eq!(row.get(&[0]), t.get(&[i, 0]));
+            assert_eq!(row.get(&[1]), t.get(&[i, 1]));
+        }
+
+        let loss: Value = t.rows().map(|row| row.sum().into_scalar()).sum();
+        loss.backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![1.0; 6]);
+    }
+
+    #[test]
+    fn test_pad() {
+        let t = Tensor::from(vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+
+        let padded = t.pad(&[(1, 1), (1, 1)]);
+
+        assert_eq!(padded.shape(), vec![4, 4]);
+        assert_eq!(
+            padded.to_vec(),
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 0.0, 0.0, 3.0, 4.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(padded.get(&[2, 1]), t.get(&[1, 0]));
+
+        (&padded * &padded).sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![2.0, 4.0, 6.0, 8.0]);
+        assert_eq!(padded.gradient().get(&[0, 0]).data(), 0.0);
+        assert_eq!(t.pad(&[(0, 0), (2, 0)]).shape(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_sum_to_shape() {
+        let t = Tensor::from_slice(&(1..=12).map(|x| x as f64).collect::<Vec<f64>>(), vec![4, 3]);
+
+        let kept = t.sum_to_shape(vec![1, 3]);
+        let dropped = t.sum_to_shape(vec![3]);
+
+        assert_eq!(kept.shape(), vec![1, 3]);
+        assert_eq!(kept.to_vec(), vec![22.0, 26.0, 30.0]);
+        assert_eq!(dropped.shape(), vec![3]);
+        assert_eq!(dropped.to_vec(), vec![22.0, 26.0, 30.0]);
+        assert_eq!(t.sum_to_shape(vec![4, 1]).to_vec(), vec![6.0, 15.0, 24.0, 33.0]);
+        assert_eq!(t.sum_to_shape(vec![1]).to_vec(), vec![78.0]);
+
+        let x = Tensor::from(vec![1.0, 2.0, 3.0]);
+        let grown = x.broadcast_to(vec![2, 3]);
+
+        assert!(grown.sum_to_shape(x.shape()).all_close(&(&x * 2.0), 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be summed to")]
+    fn test_sum_to_shape_incompatible() {
+        Tensor::from_slice(&[1.0; 6], vec![2, 3]).sum_to_shape(vec![2]);
+    }
+
+    #[test]
+    fn test_split() {
+        let t = Tensor::from(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let chunks = t.split(&[2, 4], 0);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].shape(), vec![2]);
+        assert_eq!(chunks[0].to_vec(), vec![1.0, 2.0]);
+        assert_eq!(chunks[1].shape(), vec![4]);
+        assert_eq!(chunks[1].to_vec(), vec![3.0, 4.0, 5.0, 6.0]);
+
+        let loss = &chunks[0].sum().into_scalar() + &(&chunks[1].sum().into_scalar() * &Value::from(3.0));
+        loss.backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![1.0, 1.0, 3.0, 3.0, 3.0, 3.0]);
+
+        let m = Tensor::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        let columns = m.split(&[1, 2], 1);
+
+        assert_eq!(columns[0].shape(), vec![2, 1]);
+        assert_eq!(columns[0].to_vec(), vec![1.0, 4.0]);
+        assert_eq!(columns[1].to_vec(), vec![2.0, 3.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "do not add up")]
+    fn test_split_bad_sizes() {
+        Tensor::from(vec![1.0, 2.0, 3.0]).split(&[1, 1], 0);
+    }
+
+    #[test]
+    fn test_flatten() {
+        let t = Tensor::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+
+        let flat = t.flatten();
+
+        assert_eq!(flat.shape(), vec![6]);
+        assert_eq!(flat.strides(), vec![1]);
+        assert_eq!(flat.to_vec(), t.to_vec());
+        assert_eq!(t.shape(), vec![2, 3]);
+
+        flat.set(&[4], Value::from(9.0));
+
+        assert_eq!(t.get(&[1, 1]).data(), 9.0);
+    }
+
+    #[test]
+    fn test_squeeze_unsqueeze() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![1, 3, 1]);
+
+        let squeezed = t.squeeze(2).squeeze(0);
+
+        assert_eq!(squeezed.shape(), vec![3]);
+        assert_eq!(squeezed.strides(), vec![1]);
+        assert_eq!(squeezed.get(&[1]), t.get(&[0, 1, 0]));
+
+        let unsqueezed = squeezed.unsqueeze(1);
+
+        assert_eq!(unsqueezed.shape(), vec![3, 1]);
+        assert_eq!(unsqueezed.strides(), vec![1, 1]);
+        assert_eq!(squeezed.unsqueeze(0).shape(), vec![1, 3]);
+
+        unsqueezed.sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot squeeze axis 1")]
+    fn test_squeeze_non_unit_axis() {
+        Tensor::from_slice(&[1.0, 2.0, 3.0], vec![1, 3]).squeeze(1);
+    }
+
+    #[test]
+    fn test_outer() {
+        let a = Tensor::from(vec![1.0, 2.0]);
+        let b = Tensor::from(vec![3.0, 4.0, 5.0]);
+
+        let c = a.outer(&b);
+
+        assert_eq!(c.shape(), vec![2, 3]);
+        assert_eq!(c.to_vec(), vec![3.0, 4.0, 5.0, 6.0, 8.0, 10.0]);
+
+        c.sum().backward();
+
+        assert_eq!(a.gradient().to_vec(), vec![12.0, 12.0]);
+        assert_eq!(b.gradient().to_vec(), vec![3.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn test_conv1d() {
+        let x = Tensor::from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0], vec![5]);
+        let w = Tensor::from_slice(&[1.0, 0.0, -1.0], vec![3]);
+
+        let y = x.conv1d(&w, 1, 1);
+
+        assert_eq!(y.shape(), vec![5]);
+        assert_eq!(y.to_vec(), vec![-2.0, -2.0, -2.0, -2.0, 4.0]);
+
+        y.sum().backward();
+
+        assert_eq!(w.gradient().to_vec(), vec![10.0, 15.0, 14.0]);
+        assert_eq!(x.gradient().to_vec(), vec![1.0, 0.0, 0.0, 0.0, -1.0]);
+
+        assert_eq!(x.conv1d(&w, 2, 0).to_vec(), vec![-2.0, -2.0]);
+
+        // windows that fall entirely in the padding are constant zeros
+        let edges = Tensor::from(vec![2.0]).conv1d(&Tensor::from(vec![3.0]), 1, 1);
+
+        assert_eq!(edges.to_vec(), vec![0.0, 6.0, 0.0]);
+        assert!(!edges.get(&[0]).requires_grad());
+    }
+
+    #[test]
+    fn test_matmul_empty_inner_dimension() {
+        let a = Tensor::from_slice(&[], vec![2, 0]);
+        let b = Tensor::from_slice(&[], vec![0, 3]);
+
+        let c = a.matmul(&b);
+
+        assert_eq!(c.shape(), vec![2, 3]);
+        assert_eq!(c.to_vec(), vec![0.0; 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "shape mismatch")]
+    fn test_matmul_shape_mismatch() {
+        let a = Tensor::ones(vec![2, 3]);
+        let b = Tensor::ones(vec![2, 3]);
+
+        a.matmul(&b);
+    }
+
+    #[test]
+    fn test_from_slice_to_vec() {
+        let data = vec![1.5, -2.0, 0.0, 4.25];
+
+        let t = Tensor::from_slice(&data, vec![2, 2]);
+
+        assert_eq!(t.shape(), vec![2, 2]);
+        assert_eq!(t.get(&[1, 0]).data(), 0.0);
+        assert_eq!(t.to_vec(), data);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot fill shape")]
+    fn test_from_slice_size_mismatch() {
+        Tensor::from_slice(&[1.0, 2.0, 3.0], vec![2, 2]);
+    }
+
+    #[test]
+    fn test_matmul_batched() {
+        let a = Tensor::new((1..=12).map(|x| Value::from(x as f64)).collect(), vec![2, 2, 3]);
+        let b = Tensor::new((1..=12).map(|x| Value::from(-(x as f64))).collect(), vec![2, 3, 2]);
+
+        let c = a.matmul(&b);
+
+        assert_eq!(c.shape(), vec![2, 2, 2]);
+        for batch in 0..2 {
+            let a_slice = Tensor::new(a.data()[batch * 6..(batch + 1) * 6].to_vec(), vec![2, 3]);
+            let b_slice = Tensor::new(b.data()[batch * 6..(batch + 1) * 6].to_vec(), vec![3, 2]);
+
+            assert_eq!(c.to_vec()[batch * 4..(batch + 1) * 4], a_slice.matmul(&b_slice).to_vec());
+        }
+
+        // only the second batch reaches the loss, so the first gets no gradient
+        let mask = Tensor::from_slice(&[0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0], vec![2, 2, 2]);
+        (&c * &mask).sum().backward();
+
+        let gradient = a.gradient().to_vec();
+        assert_eq!(gradient[..6], vec![0.0; 6]);
+        assert_eq!(gradient[6..], vec![-15.0, -19.0, -23.0, -15.0, -19.0, -23.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "batch size mismatch")]
+    fn test_matmul_batch_mismatch() {
+        Tensor::ones(vec![2, 2, 3]).matmul(&Tensor::ones(vec![3, 3, 2]));
+    }
+
+    #[test]
+    fn test_scalar_round_trip() {
+        let v = Value::from(2.5);
+
+        let t = Tensor::scalar(v.clone());
+
+        assert_eq!(t.shape(), vec![1]);
+        assert_eq!(t.into_scalar(), v);
+
+        let x = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![3]);
+        let total = x.sum().into_scalar();
+        (&total * &total).backward();
+
+        assert_eq!(total.data(), 6.0);
+        assert_eq!(x.gradient().to_vec(), vec![12.0; 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "single element")]
+    fn test_into_scalar_non_scalar() {
+        Tensor::zeros(vec![2]).into_scalar();
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let t = Tensor::from(vec![1.0, 2.0, 3.0]);
+
+        assert_eq!(t.shape(), vec![3]);
+        assert_eq!(t.to_vec(), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_from_nested_vec() {
+        let t = Tensor::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+
+        assert_eq!(t.shape(), vec![2, 3]);
+        assert_eq!(t.strides(), vec![3, 1]);
+        assert_eq!(t.to_vec(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(t.get(&[1, 0]).data(), 4.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "row 1 has 2 elements")]
+    fn test_from_ragged_nested_vec() {
+        let _ = Tensor::from(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0]]);
+    }
+
+    #[test]
+    fn test_all_close() {
+        let t = Tensor::from(vec![1.0, 2.0]);
+
+        assert!(t.all_close(&Tensor::from(vec![1.0 + 1e-12, 2.0 - 1e-12]), 1e-9));
+        assert!(!t.all_close(&Tensor::from(vec![1.0, 2.01]), 1e-9));
+        assert!(!t.all_close(&Tensor::from(vec![vec![1.0, 2.0]]), 1e-9));
+    }
+
+    #[test]
+    fn test_strides() {
+        assert_eq!(Tensor::zeros(vec![4]).strides(), vec![1]);
+        assert_eq!(Tensor::zeros(vec![2, 3]).strides(), vec![3, 1]);
+        assert_eq!(Tensor::zeros(vec![2, 3, 4]).strides(), vec![12, 4, 1]);
+    }
+
+    #[test]
+    fn test_add_broadcast() {
+        let m = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+        let bias = Tensor::new(vec![Value::from(10.0), Value::from(20.0), Value::from(30.0)], vec![3]);
+        let g = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        let out = &m + &bias;
+
+        assert_eq!(out.shape(), vec![2, 3]);
+        assert_eq!(
+            out.data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]
+        );
+
+        // weight the output so every position has a distinct upstream gradient
+        (&out * &g).sum().backward();
+
+        assert_eq!(bias.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![5.0, 7.0, 9.0]);
+        assert_eq!(
+            m.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_mul_broadcast_column() {
+        let m = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+        let column = Tensor::new(vec![Value::from(2.0), Value::from(3.0)], vec![2, 1]);
+
+        let out = &m * &column;
+
+        assert_eq!(out.shape(), vec![2, 3]);
+        assert_eq!(
+            out.data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![2.0, 4.0, 6.0, 12.0, 15.0, 18.0]
+        );
+
+        out.sum().backward();
+
+        assert_eq!(column.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![6.0, 15.0]);
+    }
+
+    #[test]
+    fn test_broadcast_to() {
+        let row = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![1, 3]);
+        let weights = Tensor::from_slice(&(1..=12).map(|x| x as f64).collect::<Vec<f64>>(), vec![4, 3]);
+
+        let expanded = row.broadcast_to(vec![4, 3]);
+
+        assert_eq!(expanded.shape(), vec![4, 3]);
+        assert_eq!(expanded.to_vec(), [1.0, 2.0, 3.0].repeat(4));
+
+        (&expanded * &weights).sum().backward();
+
+        // each element receives the sum of its column of weights
+        assert_eq!(row.gradient().to_vec(), vec![22.0, 26.0, 30.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be broadcast to")]
+    fn test_broadcast_to_incompatible() {
+        Tensor::ones(vec![2, 3]).broadcast_to(vec![3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be broadcast")]
+    fn test_add_broadcast_incompatible() {
+        let _ = &Tensor::ones(vec![2, 3]) + &Tensor::ones(vec![2]);
+    }
+
+    #[test]
+    fn test_sum() {
+        let t = Tensor::new((1..=4).map(|x| Value::from(x as f64)).collect(), vec![2, 2]);
+
+        let s = t.sum();
+
+        assert_eq!(s.shape(), vec![1]);
+        assert_eq!(s.data()[0].data(), 10.0);
+
+        s.backward();
+
+        assert_eq!(t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![1.0; 4]);
+    }
+
+    #[test]
+    fn test_sum_axis() {
+        let t = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        let rows = t.sum_axis(0);
+        let columns = t.sum_axis(1);
+
+        assert_eq!(rows.shape(), vec![3]);
+        assert_eq!(rows.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![5.0, 7.0, 9.0]);
+        assert_eq!(columns.shape(), vec![2]);
+        assert_eq!(columns.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![6.0, 15.0]);
+
+        let weights = Tensor::new(vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)], vec![3]);
+        (&rows * &weights).sum().backward();
+
+        assert_eq!(
+            t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn test_max_argmax() {
+        let t = Tensor::new(
+            vec![Value::from(1.0), Value::from(7.0), Value::from(-3.0), Value::from(4.0)],
+            vec![2, 2],
+        );
+
+        let m = t.max();
+
+        assert_eq!(m.data(), 7.0);
+        assert_eq!(t.argmax(), 1);
+
+        m.backward();
+
+        assert_eq!(t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_argmax_tie() {
+        let t = Tensor::new(vec![Value::from(2.0), Value::from(5.0), Value::from(5.0)], vec![3]);
+
+        assert_eq!(t.argmax(), 1);
+    }
+
+    #[test]
+    fn test_max_axis() {
+        let t = Tensor::new(
+            vec![1.0, 9.0, 3.0, 4.0, 5.0, 6.0].into_iter().map(Value::from).collect(),
+            vec![2, 3],
+        );
+
+        let rows = t.max_axis(1);
+        let columns = t.max_axis(0);
+
+        assert_eq!(rows.shape(), vec![2]);
+        assert_eq!(rows.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![9.0, 6.0]);
+        assert_eq!(columns.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![4.0, 9.0, 6.0]);
+
+        rows.sum().backward();
+
+        assert_eq!(
+            t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_mean() {
+        let t = Tensor::new((1..=4).map(|x| Value::from(x as f64)).collect(), vec![4]);
+
+        let m = t.mean();
+
+        assert_eq!(m.shape(), vec![1]);
+        assert_eq!(m.data()[0].data(), 2.5);
+
+        m.backward();
+
+        assert_eq!(t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![0.25; 4]);
+    }
+
+    #[test]
+    fn test_mean_axis() {
+        let t = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        let m = t.mean_axis(1);
+
+        assert_eq!(m.shape(), vec![2]);
+        assert_eq!(m.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![2.0, 5.0]);
+
+        m.sum().backward();
+
+        for v in t.gradient().data() {
+            assert!((v.data() - 1.0 / 3.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_relu() {
+        let t = Tensor::new(
+            vec![Value::from(-2.0), Value::from(0.5), Value::from(0.0), Value::from(3.0)],
+            vec![2, 2],
+        );
+
+        let r = t.relu();
+
+        assert_eq!(r.shape(), vec![2, 2]);
+        assert_eq!(r.data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![0.0, 0.5, 0.0, 3.0]);
+
+        r.sum().backward();
+
+        assert_eq!(t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(), vec![0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_tanh_sigmoid() {
+        let t = Tensor::new(vec![Value::from(-1.0), Value::from(0.0), Value::from(2.0)], vec![3]);
+
+        let tanh = t.tanh();
+        let sigmoid = t.sigmoid();
+
+        assert_eq!(tanh.shape(), vec![3]);
+        assert_eq!(sigmoid.shape(), vec![3]);
+        for (i, x) in [-1.0_f64, 0.0, 2.0].iter().enumerate() {
+            assert!((tanh.data()[i].data() - x.tanh()).abs() < 1e-12);
+            assert!((sigmoid.data()[i].data() - 1.0 / (1.0 + (-x).exp())).abs() < 1e-12);
+        }
+
+        tanh.sum().backward();
+
+        assert_eq!(t.gradient().data()[1].data(), 1.0);
+    }
+
+    #[test]
+    fn test_powf() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![3]);
+
+        let squared = t.powf(2.0);
+        squared.sum().backward();
+
+        assert_eq!(squared.to_vec(), vec![1.0, 4.0, 9.0]);
+        assert_eq!(t.gradient().to_vec(), vec![2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_exp_ln() {
+        let t = Tensor::from_slice(&[0.5, 1.0, 4.0], vec![3]);
+
+        let exp = t.exp();
+        let ln = t.ln();
+
+        for (i, x) in [0.5_f64, 1.0, 4.0].iter().enumerate() {
+            assert!((exp.to_vec()[i] - x.exp()).abs() < 1e-12);
+            assert!((ln.to_vec()[i] - x.ln()).abs() < 1e-12);
+        }
+
+        ln.sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![2.0, 1.0, 0.25]);
+
+        t.zero_grad();
+        exp.sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), exp.to_vec());
+    }
+
+    #[test]
+    fn test_softmax() {
+        let logits = [1.0_f64, 2.0, 3.0];
+        let t = Tensor::new(logits.iter().map(|&x| Value::from(x)).collect(), vec![3]);
+
+        let s = t.softmax(0);
+        let probabilities: Vec<f64> = s.data().iter().map(|v| v.data()).collect();
+
+        assert_eq!(s.shape(), vec![3]);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        // for upstream w, d(w . s)/dx_i = s_i * (w_i - w . s)
+        let w = [0.5, -1.0, 2.0];
+        let weights = Tensor::new(w.iter().map(|&x| Value::from(x)).collect(), vec![3]);
+        (&s * &weights).sum().backward();
+
+        let dot: f64 = w.iter().zip(probabilities.iter()).map(|(w, s)| w * s).sum();
+        for (i, v) in t.gradient().data().iter().enumerate() {
+            assert!((v.data() - probabilities[i] * (w[i] - dot)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_softmax_axis() {
+        let t = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        let columns = t.softmax(0);
+        let rows = t.softmax(1);
+
+        for total in columns.sum_axis(0).data().iter().chain(rows.sum_axis(1).data().iter()) {
+            assert!((total.data() - 1.0).abs() < 1e-12);
+        }
+        // shifting a row by a constant leaves its softmax unchanged
+        assert!((rows.get(&[0, 2]).data() - rows.get(&[1, 2]).data()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_randn_seeded() {
+        let t = Tensor::randn(vec![2, 2], 1.0, 0.5, &mut Rng::new(42));
+        let again = Tensor::randn(vec![2, 2], 1.0, 0.5, &mut Rng::new(42));
+
+        assert_eq!(t.shape(), vec![2, 2]);
+        assert_eq!(t.to_vec(), again.to_vec());
+        for (v, expected) in t.data().iter().zip([1.4411244531111345, 0.7745750621405699, 1.0941763170579657, 1.1097931895953805].iter()) {
+            assert!((v.data() - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_rand_uniform_seeded() {
+        let t = Tensor::rand_uniform(vec![3], -2.0, 2.0, &mut Rng::new(7));
+
+        for (v, expected) in t.data().iter().zip([-0.44068100643491404, -1.9328468218873756, 1.6030427224275337].iter()) {
+            assert!((v.data() - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_xavier_kaiming() {
+        let mut rng = Rng::new(0);
+        let xavier = Tensor::xavier(vec![4, 2], &mut rng);
+        let kaiming = Tensor::kaiming(vec![200, 100], &mut rng);
+
+        assert_eq!(xavier.shape(), vec![4, 2]);
+        assert!(xavier.data().iter().all(|v| v.data().abs() <= 1.0));
+
+        let mean = kaiming.mean().data()[0].data();
+        let variance = kaiming.data().iter().map(|v| (v.data() - mean).powi(2)).sum::<f64>() / kaiming.size() as f64;
+        assert!(mean.abs() < 0.01);
+        assert!((variance - 0.01).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_layer_norm() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 4.0, 9.0], vec![4]);
+
+        let normalized = t.layer_norm(0, 1e-5).to_vec();
+
+        let mean = normalized.iter().sum::<f64>() / 4.0;
+        let variance = normalized.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 4.0;
+        assert!(mean.abs() < 1e-12);
+        assert!((variance - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_layer_norm_grad_check() {
+        let inputs: Vec<Value> = [0.3, -1.2, 2.0].iter().map(|&x| Value::from(x)).collect();
+        let weights = [1.0, -2.0, 0.5];
+
+        let f = |x: &[Value]| {
+            let normalized = Tensor::new(x.to_vec(), vec![3]).layer_norm(0, 1e-5);
+            normalized.data().iter().zip(weights).map(|(v, w)| v * &Value::from(w)).sum()
+        };
+
+        assert!(crate::grad_check(f, &inputs, 1e-6).iter().all(|&d| d < 1e-6));
+    }
+
+    #[test]
+    fn test_layer_norm_axis() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 3.0, 10.0, 20.0, 30.0], vec![2, 3]);
+
+        let normalized = t.layer_norm(1, 0.0);
+
+        // each row is normalized independently, so scaling a row changes nothing
+        for (a, b) in normalized.to_vec()[..3].iter().zip(normalized.to_vec()[3..].iter()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_dropout() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![2, 3]);
+
+        let dropped = t.dropout(0.5, true, 9);
+
+        assert_eq!(dropped.shape(), vec![2, 3]);
+        assert_eq!(dropped.to_vec(), vec![2.0, 4.0, 0.0, 8.0, 0.0, 0.0]);
+
+        dropped.sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![2.0, 2.0, 0.0, 2.0, 0.0, 0.0]);
+        assert!(!dropped.get(&[0, 2]).requires_grad());
+    }
+
+    #[test]
+    fn test_dropout_eval_is_identity() {
+        let t = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![3]);
+
+        let out = t.dropout(0.5, false, 9);
+
+        assert_eq!(out.data(), t.data());
+    }
+
+    #[test]
+    fn test_where() {
+        let cond = Tensor::from(vec![1.0, 0.0, 0.0, 1.0]);
+        let a = Tensor::from(vec![1.0, 2.0, 3.0, 4.0]);
+        let b = Tensor::from(vec![-1.0, -2.0, -3.0, -4.0]);
+
+        let selected = Tensor::where_(&cond, &a, &b);
+
+        assert_eq!(selected.to_vec(), vec![1.0, -2.0, -3.0, 4.0]);
+
+        (&selected * &selected).sum().backward();
+
+        assert_eq!(a.gradient().to_vec(), vec![2.0, 0.0, 0.0, 8.0]);
+        assert_eq!(b.gradient().to_vec(), vec![0.0, -4.0, -6.0, 0.0]);
+        assert_eq!(cond.gradient().to_vec(), vec![0.0; 4]);
+
+        // a [1] branch broadcasts against the others
+        let zero = Tensor::from(vec![0.0]);
+        let mask = Tensor::from(vec![1.0, 0.0, 1.0, 0.0]);
+
+        assert_eq!(Tensor::where_(&mask, &a, &zero).to_vec(), vec![1.0, 0.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_activate() {
+        let t = Tensor::from(vec![-2.0, 0.0, 3.0]);
+
+        assert_eq!(t.activate(Activation::Relu).to_vec(), vec![0.0, 0.0, 3.0]);
+        let identity = t.activate(Activation::Identity);
+        identity.set(&[0], Value::from(7.0));
+
+        assert_eq!(identity.data()[1..], t.data()[1..]);
+        assert_eq!(t.to_vec(), vec![-2.0, 0.0, 3.0]);
+        assert_eq!(t.activate(Activation::LeakyRelu(0.1)).to_vec(), vec![-0.2, 0.0, 3.0]);
+        assert!(t.activate(Activation::Tanh).all_close(&t.tanh(), 0.0));
+        assert!(t.activate(Activation::Sigmoid).all_close(&Tensor::from(vec![0.119_202_922_022_117_57, 0.5, 0.952_574_126_822_433_5]), 1e-12));
+
+        t.activate(Activation::LeakyRelu(0.1)).sum().backward();
+
+        assert_eq!(t.gradient().to_vec(), vec![0.1, 0.1, 1.0]);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let t = Tensor::from(vec![vec![1.0, 2.0, 3.0], vec![1000.0, 0.0, -1000.0]]);
+
+        let log_probs = t.log_softmax(1);
+
+        assert_eq!(log_probs.shape(), vec![2, 3]);
+        assert!(log_probs.to_vec().iter().all(|x| x.is_finite()));
+        for sum in log_probs.exp().sum_axis(1).to_vec() {
+            assert!((sum - 1.0).abs() < 1e-12);
+        }
+        assert!(log_probs.get(&[1, 2]).approx_eq(-2000.0, 1e-9));
+
+        let expected = t.softmax(1).ln();
+        assert!(log_probs.get(&[0, 0]).approx_eq(expected.get(&[0, 0]).data(), 1e-12));
+    }
+
+    #[test]
+    fn test_map() {
+        let x = Tensor::from(vec![vec![1.0, -2.0], vec![3.0, 0.5]]);
+
+        let squared = x.map(|v| v * v);
+
+        assert_eq!(squared.shape(), vec![2, 2]);
+        assert_eq!(squared.to_vec(), vec![1.0, 4.0, 9.0, 0.25]);
+
+        squared.sum().backward();
+
+        assert_eq!(x.gradient().to_vec(), vec![2.0, -4.0, 6.0, 1.0]);
+    }
+
+    #[test]
+    fn test_zip_map() {
+        let a = Tensor::from(vec![1.0, 5.0, 3.0]);
+        let b = Tensor::from(vec![4.0, 2.0, 6.0]);
+
+        let max = a.zip_map(&b, Value::max);
+
+        assert_eq!(max.to_vec(), vec![4.0, 5.0, 6.0]);
+
+        max.sum().backward();
+
+        assert_eq!(a.gradient().to_vec(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(b.gradient().to_vec(), vec![1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "zip_map expects matching shapes")]
+    fn test_zip_map_shape_mismatch() {
+        Tensor::from(vec![1.0, 2.0]).zip_map(&Tensor::from(vec![1.0]), |u, v| u + v);
+    }
+
+    #[test]
+    fn test_forward_only() {
+        let x = Tensor::from_slice(&[1.0, 2.0, 3.0], vec![3]);
+
+        let y = (&x * 2.0).tanh().forward_only();
+
+        assert_eq!(y.shape(), vec![3]);
+        assert!(y.all_close(&(&x * 2.0).tanh(), 0.0));
+
+        y.sum().backward();
+
+        assert_eq!(x.gradient().to_vec(), vec![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let t = Tensor::new((0..6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        let tt = t.transpose();
+
+        assert_eq!(tt.shape(), vec![3, 2]);
+        assert_eq!(tt.strides(), vec![2, 1]);
+        for i in 0..3 {
+            for j in 0..2 {
+                assert_eq!(tt.data()[i * 2 + j].data(), t.data()[j * 3 + i].data());
+            }
+        }
+
+        let weights = Tensor::new((1..=6).map(|x| Value::from(x as f64)).collect(), vec![3, 2]);
+        (&tt * &weights).sum().backward();
+
+        // t[j][i] receives the weight at tt[i][j]
+        assert_eq!(
+            t.gradient().data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            vec![1.0, 3.0, 5.0, 2.0, 4.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_reshape() {
+        let t = Tensor::new((0..6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        t.reshape(vec![3, 2]);
+
+        assert_eq!(t.shape(), vec![3, 2]);
+        assert_eq!(t.strides(), vec![2, 1]);
+        assert_eq!(t.transpose().data()[1].data(), 2.0);
+    }
+
+    #[test]
+    fn test_get() {
+        let t = Tensor::new((0..6).map(|x| Value::from(x as f64)).collect(), vec![2, 3]);
+
+        assert_eq!(t.get(&[1, 2]).data(), 5.0);
+        assert_eq!(t.get(&[1, 2]), t.data()[5]);
+        assert_eq!(t.get(&[0, 1]).data(), 1.0);
+    }
+
+    #[test]
+    fn test_set() {
+        let t = Tensor::zeros(vec![2, 3]);
+        let v = Value::from(7.0);
+
+        t.set(&[1, 2], v.clone());
+
+        assert_eq!(t.data()[5].data(), 7.0);
+        assert_eq!(t.data().iter().filter(|v| v.data() != 0.0).count(), 1);
+
+        t.sum().backward();
+
+        assert_eq!(v.gradient(), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_get_out_of_range() {
+        Tensor::zeros(vec![2, 3]).get(&[0, 3]);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("regrad-test-save-load-{}.bin", std::process::id()));
+        let t = Tensor::new(vec![-1.5, 0.0, 2.25, 3.0, 1e-300, f64::MAX].into_iter().map(Value::from).collect(), vec![2, 3]);
+
+        t.save(&path).unwrap();
+        let loaded = Tensor::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.shape(), vec![2, 3]);
+        assert_eq!(loaded.strides(), vec![3, 1]);
+        assert_eq!(
+            loaded.data().iter().map(|v| v.data()).collect::<Vec<f64>>(),
+            t.data().iter().map(|v| v.data()).collect::<Vec<f64>>()
+        );
+    }
+
+    #[test]
+    fn test_load_bad_magic() {
+        let path = std::env::temp_dir().join(format!("regrad-test-bad-magic-{}.bin", std::process::id()));
+        fs::write(&path, b"not a tensor").unwrap();
+
+        let result = Tensor::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(error) if error.kind() == io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_load_corrupt_header() {
+        let path = std::env::temp_dir().join(format!("regrad-test-corrupt-header-{}.bin", std::process::id()));
+        let header = |rank: u64, dims: &[u64]| {
+            let mut bytes = CHECKPOINT_MAGIC.to_vec();
+            bytes.extend(rank.to_le_bytes());
+            dims.iter().for_each(|dim| bytes.extend(dim.to_le_bytes()));
+            bytes.extend(1.0_f64.to_le_bytes());
+            bytes
+        };
+
+        for bytes in [header(u64::MAX, &[]), header(2, &[u64::MAX, u64::MAX]), header(2, &[1 << 20, 1 << 20])] {
+            fs::write(&path, bytes).unwrap();
+
+            let result = Tensor::load(&path);
+
+            assert!(matches!(result, Err(error) if error.kind() == io::ErrorKind::InvalidData));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_view() {
+        let t = Tensor::from_slice(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0], vec![6]);
+
+        let v = t.view(vec![2, 3]);
+
+        assert_eq!(t.shape(), vec![6]);
+        assert_eq!(v.shape(), vec![2, 3]);
+        assert_eq!(v.strides(), vec![3, 1]);
+        assert_eq!(v.get(&[1, 0]).data(), 3.0);
+
+        v.set(&[1, 2], Value::from(50.0));
+        t.set(&[0], Value::from(-1.0));
+
+        assert_eq!(t.get(&[5]).data(), 50.0);
+        assert_eq!(v.get(&[0, 0]).data(), -1.0);
+        assert_eq!(t.to_vec(), v.to_vec());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot view")]
+    fn test_view_size_mismatch() {
+        Tensor::zeros(vec![6]).view(vec![4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot reshape")]
+    fn test_reshape_size_mismatch() {
+        Tensor::zeros(vec![2, 3]).reshape(vec![4]);
+    }
+}
+
+// the elements live behind their own Rc so that views can share them while
+// keeping a separate shape and strides
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TensorInternal {
+    data: Rc<RefCell<Vec<Value>>>,
     shape: Vec<usize>,
     strides: Vec<usize>,
     size: usize,
@@ -314,7 +2117,7 @@ impl TensorInternal {
         size: usize,
     ) -> TensorInternal {
         TensorInternal {
-            data,
+            data: Rc::new(RefCell::new(data)),
             shape,
             strides,
             size,
@@ -322,11 +2125,20 @@ impl TensorInternal {
     }
 }
 
+impl Hash for TensorInternal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.data.borrow().hash(state);
+        self.shape.hash(state);
+        self.strides.hash(state);
+        self.size.hash(state);
+    }
+}
+
 fn compute_strides(shape: Vec<usize>) -> Vec<usize> {
     shape
         .iter()
-        .rev()
         .skip(1)
+        .rev()
         .fold(vec![1], |mut acc, &s| {
             acc.push(acc.last().unwrap() * s);
             acc
@@ -336,3 +2148,52 @@ fn compute_strides(shape: Vec<usize>) -> Vec<usize> {
         .cloned()
         .collect()
 }
+
+fn fans(shape: &[usize]) -> (usize, usize) {
+    assert!(!shape.is_empty(), "cannot compute fan in and fan out of an empty shape");
+
+    (shape[0], shape[1..].iter().product())
+}
+
+// numpy-style broadcasting: shapes are aligned from the right and a dimension
+// of size 1 (or a missing one) is stretched to match the other operand
+fn broadcast_shape(u: &[usize], v: &[usize]) -> Vec<usize> {
+    let rank = u.len().max(v.len());
+
+    (0..rank)
+        .map(|i| {
+            let ud = if i < rank - u.len() { 1 } else { u[i - (rank - u.len())] };
+            let vd = if i < rank - v.len() { 1 } else { v[i - (rank - v.len())] };
+
+            match (ud, vd) {
+                (ud, vd) if ud == vd => ud,
+                (1, vd) => vd,
+                (ud, 1) => ud,
+                _ => panic!("shapes {:?} and {:?} cannot be broadcast together", u, v),
+            }
+        })
+        .collect()
+}
+
+// expands the data of t to shape, repeating (not copying) the shared values so
+// that gradients flowing into repeated positions accumulate in the original
+fn broadcast_data(t: &Tensor, shape: &[usize]) -> Vec<Value> {
+    let (data, t_shape, t_strides) = (t.data(), t.shape(), t.strides());
+    let offset = shape.len() - t_shape.len();
+    let strides = compute_strides(shape.to_vec());
+    let size = shape.iter().product();
+
+    (0..size)
+        .map(|flat| {
+            let index = t_shape.iter().enumerate().fold(0, |acc, (i, &dim)| {
+                let position = flat / strides[i + offset] % shape[i + offset];
+                if dim == 1 {
+                    acc
+                } else {
+                    acc + position * t_strides[i]
+                }
+            });
+            data[index].clone()
+        })
+        .collect()
+}
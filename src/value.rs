@@ -1,14 +1,105 @@
 use std::{
-    cell::{Ref, RefCell},
-    fmt::{Debug, Formatter, Result},
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Display, Formatter, Result},
     hash::Hash,
-    ops::{Add, Deref, Mul, Neg, Sub},
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Deref, Div, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+#[cfg(not(feature = "threadsafe"))]
+use std::{
+    cell::{Ref, RefCell},
     rc::Rc,
 };
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg(feature = "threadsafe")]
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// every value shares its node through Handle; the threadsafe feature swaps the
+// single-threaded Rc<RefCell> for Arc<RwLock> so graphs can cross threads
+#[cfg(not(feature = "threadsafe"))]
+type Shared<T> = Rc<T>;
+#[cfg(not(feature = "threadsafe"))]
+type Lock<T> = RefCell<T>;
+#[cfg(not(feature = "threadsafe"))]
+type ReadGuard<'a, T> = Ref<'a, T>;
+
+#[cfg(feature = "threadsafe")]
+type Shared<T> = Arc<T>;
+#[cfg(feature = "threadsafe")]
+type Lock<T> = RwLock<T>;
+#[cfg(feature = "threadsafe")]
+type ReadGuard<'a, T> = RwLockReadGuard<'a, T>;
+
+type Handle = Shared<Lock<ValueInternal>>;
+
+// gives RwLock the borrow and borrow_mut of RefCell so call sites are shared
+#[cfg(feature = "threadsafe")]
+trait LockExt<T> {
+    fn borrow(&self) -> RwLockReadGuard<'_, T>;
+    fn borrow_mut(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+#[cfg(feature = "threadsafe")]
+impl<T> LockExt<T> for RwLock<T> {
+    fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap()
+    }
+
+    fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap()
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Value {
-    internal: Rc<RefCell<ValueInternal>>,
+    internal: Handle,
+}
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+    static DETECT_ANOMALY: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+// while enabled, backward panics at the first node whose data or propagated
+// gradient is NaN or infinite, naming its operation and label
+pub fn set_detect_anomaly(enabled: bool) {
+    DETECT_ANOMALY.with(|detect| detect.set(enabled));
+}
+
+pub fn is_anomaly_enabled() -> bool {
+    DETECT_ANOMALY.with(|detect| detect.get())
+}
+
+// while the returned guard is alive, new values record no graph; dropping it
+// (also during unwinding) restores whatever mode was active before
+pub fn no_grad() -> NoGradGuard {
+    let previous = GRAD_ENABLED.with(|enabled| enabled.replace(false));
+
+    NoGradGuard { previous }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub max_depth: usize,
+    pub operations: HashMap<Operation, usize>,
+}
+
+pub struct NoGradGuard {
+    previous: bool,
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
 }
 
 impl Value {
@@ -19,9 +110,35 @@ impl Value {
         t.into()
     }
 
-    fn new(internal: ValueInternal) -> Value {
+    // a named parameter, e.g. Value::leaf(0.5, "w1")
+    pub fn leaf(data: f64, label: &str) -> Value {
+        let value = Value::from(data);
+        value.set_label(label);
+
+        value
+    }
+
+    // a leaf that never collects a gradient, for the fixed operands an op
+    // creates itself (exponents, slopes, scale factors) so they don't pass for
+    // user leaves
+    pub(crate) fn constant(data: f64) -> Value {
+        let value = Value::from(data);
+        value.set_requires_grad(false);
+
+        value
+    }
+
+    // under no_grad the result is a plain leaf: no operands, no backward
+    // function and no operation tag that would claim otherwise
+    fn new(mut internal: ValueInternal) -> Value {
+        if !is_grad_enabled() {
+            internal.previous.clear();
+            internal.propagate = None;
+            internal.operation = None;
+        }
+
         Value {
-            internal: Rc::new(RefCell::new(internal)),
+            internal: Shared::new(Lock::new(internal)),
         }
     }
 
@@ -29,236 +146,2290 @@ impl Value {
         self.internal.borrow().data
     }
 
+    pub(crate) fn set_data(&self, data: f64) {
+        self.internal.borrow_mut().data = data;
+    }
+
     pub fn gradient(&self) -> f64 {
-        self.internal.borrow().gradient
+        self.internal.borrow().gradient()
+    }
+
+    // the gradient as a node; after backward_create_graph it is connected to the
+    // forward graph, so calling backward on it differentiates a second time
+    pub fn gradient_value(&self) -> Value {
+        self.internal.borrow().upstream()
+    }
+
+    pub(crate) fn set_gradient(&self, gradient: f64) {
+        self.internal.borrow_mut().gradient = Some(Value::from(gradient));
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.internal.borrow().label.clone()
+    }
+
+    pub fn set_label(&self, label: impl Into<String>) -> &Value {
+        self.internal.borrow_mut().label = Some(label.into());
+
+        self
+    }
+
+    pub fn requires_grad(&self) -> bool {
+        self.internal.borrow().requires_grad
+    }
+
+    // when false, backward leaves this node's gradient at zero; constants and
+    // frozen parameters can opt out so no gradient is accumulated for them
+    pub fn set_requires_grad(&self, requires_grad: bool) -> &Value {
+        self.internal.borrow_mut().requires_grad = requires_grad;
+
+        self
     }
 
     pub fn zero_grad(&self) {
-        self.internal.borrow_mut().gradient = 0.0;
+        self.internal.borrow_mut().gradient = None;
+    }
+
+    // zeroes this node and every ancestor, not just the leaves
+    pub fn zero_grad_graph(&self) {
+        for value in self.topo_order() {
+            value.zero_grad();
+        }
     }
 
+    // adds factor * gradient to data; pass a negative factor to descend the gradient
     pub fn update(&self, factor: f64) {
-        let gradient = self.internal.borrow().gradient;
+        let gradient = self.gradient();
         self.internal.borrow_mut().data += factor * gradient;
     }
 
+    // one gradient descent step: data -= lr * gradient
+    pub fn step(&self, lr: f64) {
+        self.update(-lr);
+    }
+
+    // compares data only, within an absolute tolerance
+    pub fn approx_eq(&self, other: f64, tol: f64) -> bool {
+        (self.data() - other).abs() <= tol
+    }
+
+    // true when both handles point at the same node; this is also what == does
+    pub fn same(&self, other: &Value) -> bool {
+        Shared::ptr_eq(&self.internal, &other.internal)
+    }
+
+    // a new leaf holding the same data, so no gradient flows back through it
+    pub fn detach(&self) -> Value {
+        Value::from(self.data())
+    }
+
+    // re-evaluates every node between the leaves and self from its operands,
+    // e.g. after an optimizer step changed leaf data, reusing the existing graph
+    // instead of building a new one. gradients are left as they are
+    pub fn recompute(&self) {
+        let _guard = no_grad();
+
+        for value in self.topo_order() {
+            let (operation, previous) = {
+                let internal = value.internal.borrow();
+                (internal.operation.clone(), internal.previous.clone())
+            };
+
+            if let Some(operation) = operation {
+                let replayed = replay(&operation, &previous).expect("node operands do not fit its operation");
+                value.set_data(replayed.data());
+            }
+        }
+    }
+
+    // turns this node into a leaf in place, keeping its data, label and a
+    // detached copy of its gradient. every handle to it sees the change, so the
+    // history it was holding can be freed (e.g. for parameters between epochs)
+    pub fn detach_graph(&self) {
+        let mut internal = self.internal.borrow_mut();
+        let previous = std::mem::take(&mut internal.previous);
+        internal.operation = None;
+        internal.propagate = None;
+        internal.gradient = internal.gradient.as_ref().map(Value::detach);
+        drop(internal);
+
+        drop(previous);
+    }
+
     pub fn backward(&self) {
-        let mut visited = std::collections::HashSet::new();
-        let mut queue = std::collections::VecDeque::new();
-        queue.push_back(self.clone());
+        self.zero_grad();
+        self.backward_accumulate(1.0);
+    }
+
+    // runs backward and snapshots the gradient of every leaf that requires one.
+    // values hash by identity, so leaves with equal data stay separate keys
+    pub fn backward_into(&self) -> HashMap<Value, f64> {
+        self.backward();
 
-        self.internal.borrow_mut().gradient = 1.0;
+        self.topo_order()
+            .into_iter()
+            .filter(|value| {
+                let internal = value.internal.borrow();
+                internal.previous.is_empty() && internal.requires_grad
+            })
+            .map(|value| {
+                let gradient = value.gradient();
+                (value, gradient)
+            })
+            .collect()
+    }
+
+    // backward, after which a retain_graph of false turns every intermediate
+    // node into a leaf (as detach_graph does) so the graph can be freed. their
+    // data and gradients stay readable, but a second backward from the same
+    // root then reaches no leaves. plain backward always retains the graph
+    pub fn backward_retain(&self, retain_graph: bool) {
+        self.backward();
+
+        if !retain_graph {
+            for value in self.topo_order() {
+                if !value.internal.borrow().previous.is_empty() {
+                    value.detach_graph();
+                }
+            }
+        }
+    }
+
+    // adds seed to the root gradient and propagates it, so leaf gradients keep
+    // accumulating across calls (e.g. over micro-batches). intermediate gradients
+    // are recomputed on every pass; call zero_grad_graph between optimizer steps
+    pub fn backward_accumulate(&self, seed: f64) {
+        let _guard = no_grad();
+
+        self.propagate_gradients(seed);
+    }
+
+    // like backward, but the gradient computation is itself recorded so that
+    // gradient_value can be differentiated again (e.g. for Hessian-vector
+    // products). leaf gradients then reference the graph they came from, so
+    // zero_grad them once they are no longer needed to release it
+    pub fn backward_create_graph(&self) {
+        self.zero_grad();
+        self.propagate_gradients(1.0);
+    }
+
+    fn propagate_gradients(&self, seed: f64) {
+        let topo = self.topo_order();
 
-        while let Some(value) = queue.pop_front() {
+        for value in topo.iter() {
+            let mut internal = value.internal.borrow_mut();
+            if !internal.previous.is_empty() {
+                internal.gradient = None;
+            }
+        }
+        accumulate(self, Value::from(seed));
+
+        let detect_anomaly = is_anomaly_enabled();
+        for value in topo.iter().rev() {
             let internal = value.internal.borrow();
-            if visited.contains(&value) {
+            if detect_anomaly {
+                check_anomaly(&internal, "data", internal.data);
+            }
+            if let (Some(propagate), Some(_)) = (internal.propagate, &internal.gradient) {
+                propagate(&internal);
+
+                if detect_anomaly {
+                    for previous in internal.previous.iter() {
+                        check_anomaly(&internal, "gradient", previous.gradient());
+                    }
+                }
+            }
+        }
+    }
+
+    // every node reachable from self, operands before their results, which is
+    // the order backward walks in reverse. the traversal is deterministic, so a
+    // given graph always yields the same order; it uses an explicit stack so
+    // that arbitrarily deep graphs cannot overflow
+    pub fn topo_order(&self) -> Vec<Value> {
+        let mut visited = HashSet::new();
+        let mut topo = vec![];
+        let mut stack = vec![(self.clone(), false)];
+
+        while let Some((value, expanded)) = stack.pop() {
+            if expanded {
+                topo.push(value);
+                continue;
+            }
+            if !visited.insert(Shared::as_ptr(&value.internal)) {
                 continue;
             }
-            visited.insert(value.clone());
 
-            if let Some(propagate) = internal.propagate {
-                propagate(&internal);
+            let previous = value.internal.borrow().previous.clone();
+            stack.push((value, true));
+            for previous in previous.into_iter().rev() {
+                if !visited.contains(&Shared::as_ptr(&previous.internal)) {
+                    stack.push((previous, false));
+                }
+            }
+        }
+
+        topo
+    }
+
+    // depth counts operations along the longest path down to a leaf, so a leaf
+    // has depth 0; an operand used twice (as in a + a) counts as two edges
+    pub fn graph_stats(&self) -> GraphStats {
+        let topo = self.topo_order();
+        let mut depths = HashMap::new();
+        let mut stats = GraphStats {
+            node_count: topo.len(),
+            edge_count: 0,
+            max_depth: 0,
+            operations: HashMap::new(),
+        };
+
+        for value in topo.iter() {
+            let internal = value.internal.borrow();
+            let depth = internal
+                .previous
+                .iter()
+                .map(|previous| depths[&Shared::as_ptr(&previous.internal)] + 1)
+                .max()
+                .unwrap_or(0);
+
+            depths.insert(Shared::as_ptr(&value.internal), depth);
+            stats.edge_count += internal.previous.len();
+            stats.max_depth = stats.max_depth.max(depth);
+            if let Some(operation) = &internal.operation {
+                *stats.operations.entry(operation.clone()).or_insert(0) += 1;
             }
+        }
+
+        stats
+    }
+
+    // one record per value and one oval per operation, in the style of micrograd's draw_dot
+    pub fn to_dot(&self) -> String {
+        let topo = self.topo_order();
+        let ids: HashMap<_, _> = topo
+            .iter()
+            .enumerate()
+            .map(|(id, value)| (Shared::as_ptr(&value.internal), id))
+            .collect();
+
+        let mut dot = String::from("digraph {\n    rankdir=LR;\n");
+        for (id, value) in topo.iter().enumerate() {
+            let internal = value.internal.borrow();
+            let label = internal.label.as_deref().unwrap_or("").replace('"', "\\\"");
+
+            dot.push_str(&format!(
+                "    n{} [shape=record, label=\"{{ {} | data {:.4} | grad {:.4} }}\"];\n",
+                id, label, internal.data, internal.gradient()
+            ));
 
-            for previous in internal.previous.iter() {
-                queue.push_back(previous.clone());
+            if let Some(operation) = &internal.operation {
+                dot.push_str(&format!("    n{}_op [shape=oval, label=\"{:?}\"];\n", id, operation));
+                dot.push_str(&format!("    n{}_op -> n{};\n", id, id));
+
+                for previous in internal.previous.iter() {
+                    dot.push_str(&format!("    n{} -> n{}_op;\n", ids[&Shared::as_ptr(&previous.internal)], id));
+                }
             }
         }
+        dot.push_str("}\n");
+
+        dot
     }
 }
 
 impl Value {
     pub fn tanh(&self) -> Value {
         let data = self.data().tanh();
-        let propagate: BackPropagteFn = |value: &Ref<ValueInternal>| {
-            let gradient = value.gradient;
-            let data = value.data;
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].tanh();
 
-            value.previous[0].internal.borrow_mut().gradient += gradient * (1.0 - data * data);
+            accumulate(&value.previous[0], &gradient * &(&Value::from(1.0) - &(&data * &data)));
         };
 
         Value::new(ValueInternal::new(
             data,
             None,
-            Some(Operation::Mul),
+            Some(Operation::Tanh),
             vec![self.clone()],
             Some(propagate),
         ))
     }
 
-    pub fn pow(&self, n: &Value) -> Value {
-        let data = self.data().powf(n.data());
-        let propagate: BackPropagteFn = |value: &Ref<ValueInternal>| {
-            let gradient = value.gradient;
-            let data = value.data;
-            let n = value.previous[1].internal.borrow().data;
+    pub fn powf(&self, n: f64) -> Value {
+        let data = self.data().powf(n);
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let base = &value.previous[0];
+            let n = value.previous[1].data();
 
-            value.previous[0].internal.borrow_mut().gradient += gradient * n * data.powf(n - 1.0);
+            accumulate(base, &(&gradient * &Value::from(n)) * &base.powf(n - 1.0));
         };
 
         Value::new(ValueInternal::new(
             data,
             None,
-            Some(Operation::Mul),
-            vec![self.clone(), n.clone()],
+            Some(Operation::Powf),
+            vec![self.clone(), Value::constant(n)],
             Some(propagate),
         ))
     }
 
-    pub fn relu(&self) -> Value {
-        let data = self.data().max(0.0);
-        let propagate: BackPropagteFn = |value: &Ref<ValueInternal>| {
-            let gradient = value.gradient;
-            let data = value.data;
+    // built from multiplications by squaring, so the result is exact and the
+    // gradient n * x^(n - 1) falls out of the product rule. x^0 is a constant 1
+    // with no edge back to x, and a negative n takes the reciprocal
+    pub fn powi(&self, n: i32) -> Value {
+        let (mut result, mut base, mut exponent) = (None::<Value>, self.clone(), n.unsigned_abs());
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = Some(match result {
+                    Some(result) => &result * &base,
+                    None => base.clone(),
+                });
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        match result {
+            Some(result) if n < 0 => result.recip(),
+            Some(result) => result,
+            None => Value::from(1.0),
+        }
+    }
+
+    pub fn powv(&self, n: &Value) -> Value {
+        let data = self.data().powf(n.data());
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let (base, n) = (&value.previous[0], &value.previous[1]);
+            let data = base.powv(n);
 
-            value.previous[0].internal.borrow_mut().gradient += gradient * ((data > 0.0) as i32 as f64);
+            accumulate(base, &(&gradient * n) * &base.powv(&(n - &Value::from(1.0))));
+            accumulate(n, &(&gradient * &data) * &base.ln());
         };
 
         Value::new(ValueInternal::new(
             data,
             None,
-            Some(Operation::Mul),
-            vec![self.clone()],
+            Some(Operation::Pow),
+            vec![self.clone(), n.clone()],
             Some(propagate),
         ))
     }
-}
 
-impl Hash for Value {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.internal.borrow().hash(state);
+    #[deprecated(note = "renamed to powv; use powf for a constant exponent")]
+    pub fn pow(&self, n: &Value) -> Value {
+        self.powv(n)
     }
-}
 
-impl Deref for Value {
-    type Target = Rc<RefCell<ValueInternal>>;
+    pub fn exp(&self) -> Value {
+        let data = self.data().exp();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].exp();
 
-    fn deref(&self) -> &Self::Target {
-        &self.internal
-    }
-}
+            accumulate(&value.previous[0], &gradient * &data);
+        };
 
-impl From<f64> for Value {
-    fn from(data: f64) -> Self {
-        Value::new(ValueInternal::new(data, None, None, vec![], None))
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Exp),
+            vec![self.clone()],
+            Some(propagate),
+        ))
     }
-}
 
-fn add(u: &Value, v: &Value) -> Value {
-    let data = u.data() + v.data();
-    let propagate: BackPropagteFn = |value: &Ref<ValueInternal>| {
-        let gradient = value.gradient;
+    pub fn ln(&self) -> Value {
+        let data = self.data().ln();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
 
-        value.previous[0].internal.borrow_mut().gradient += gradient;
-        value.previous[1].internal.borrow_mut().gradient += gradient;
-    };
+            accumulate(&value.previous[0], &gradient / &value.previous[0]);
+        };
 
-    Value::new(ValueInternal::new(
-        data,
-        None,
-        Some(Operation::Add),
-        vec![u.clone(), v.clone()],
-        Some(propagate),
-    ))
-}
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Ln),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
 
-impl Add for Value {
-    type Output = Value;
+    pub fn sqrt(&self) -> Value {
+        let data = self.data().sqrt();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].sqrt();
 
-    fn add(self, rhs: Self) -> Self::Output {
-        add(&self, &rhs)
+            accumulate(&value.previous[0], &(&gradient * &Value::from(0.5)) / &data);
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Sqrt),
+            vec![self.clone()],
+            Some(propagate),
+        ))
     }
-}
 
-impl<'a, 'b> Add<&'b Value> for &'a Value {
-    type Output = Value;
+    pub fn recip(&self) -> Value {
+        let data = self.data().recip();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].recip();
 
-    fn add(self, rhs: &'b Value) -> Self::Output {
-        add(self, rhs)
+            accumulate(&value.previous[0], neg(&(&gradient * &(&data * &data))));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Recip),
+            vec![self.clone()],
+            Some(propagate),
+        ))
     }
-}
 
-fn mul(u: &Value, v: &Value) -> Value {
-    let data = u.data() * v.data();
+    pub fn exp2(&self) -> Value {
+        let data = self.data().exp2();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].exp2();
 
-    let propagate: BackPropagteFn = |value: &Ref<ValueInternal>| {
-        let ud = value.previous[0].internal.borrow().data;
-        let vd = value.previous[1].internal.borrow().data;
+            accumulate(&value.previous[0], &(&gradient * &Value::from(std::f64::consts::LN_2)) * &data);
+        };
 
-        value.previous[0].internal.borrow_mut().gradient += value.gradient * vd;
-        value.previous[1].internal.borrow_mut().gradient += value.gradient * ud;
-    };
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Exp2),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
 
-    Value::new(ValueInternal::new(
-        data,
-        None,
-        Some(Operation::Mul),
-        vec![u.clone(), v.clone()],
-        Some(propagate),
-    ))
-}
+    pub fn sin(&self) -> Value {
+        let data = self.data().sin();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
 
-impl Mul<Value> for Value {
-    type Output = Value;
+            accumulate(&value.previous[0], &gradient * &value.previous[0].cos());
+        };
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        mul(&self, &rhs)
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Sin),
+            vec![self.clone()],
+            Some(propagate),
+        ))
     }
-}
 
-impl<'a, 'b> Mul<&'b Value> for &'a Value {
-    type Output = Value;
+    pub fn cos(&self) -> Value {
+        let data = self.data().cos();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
 
-    fn mul(self, rhs: &'b Value) -> Self::Output {
-        mul(self, rhs)
+            accumulate(&value.previous[0], neg(&(&gradient * &value.previous[0].sin())));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Cos),
+            vec![self.clone()],
+            Some(propagate),
+        ))
     }
-}
 
-impl Neg for Value {
-    type Output = Value;
+    // the tanh approximation 0.5 * x * (1 + tanh(sqrt(2 / pi) * (x + 0.044715 * x^3))),
+    // composed from existing ops so it needs no backward function of its own
+    pub fn gelu(&self) -> Value {
+        let cube = &(self * self) * self;
+        let inner = &Value::constant((2.0 / std::f64::consts::PI).sqrt()) * &(self + &(&Value::constant(0.044715) * &cube));
 
-    fn neg(self) -> Self::Output {
-        mul(&self, &Value::from(-1.0))
+        &(&Value::constant(0.5) * self) * &(&Value::constant(1.0) + &inner.tanh())
     }
-}
 
-impl<'a> Neg for &'a Value {
-    type Output = Value;
+    // ln(1 + exp(x)) computed as max(x, 0) + ln(1 + exp(-|x|)), so exp never
+    // overflows and large negative inputs don't round to ln(1) = 0 too early
+    pub fn softplus(&self) -> Value {
+        let input = self.data();
+        let data = input.max(0.0) + (-input.abs()).exp().ln_1p();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
 
-    fn neg(self) -> Self::Output {
-        mul(self, &Value::from(-1.0))
+            accumulate(&value.previous[0], &gradient * &value.previous[0].sigmoid());
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Softplus),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
+
+    pub fn sigmoid(&self) -> Value {
+        let input = self.data();
+        // only ever exponentiate a non-positive number so large inputs cannot overflow
+        let data = if input >= 0.0 {
+            1.0 / (1.0 + (-input).exp())
+        } else {
+            let e = input.exp();
+            e / (1.0 + e)
+        };
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.previous[0].sigmoid();
+
+            accumulate(&value.previous[0], &(&gradient * &data) * &(&Value::from(1.0) - &data));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Sigmoid),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
+
+    pub fn relu(&self) -> Value {
+        let data = self.data().max(0.0);
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let data = value.data;
+
+            accumulate(&value.previous[0], &gradient * &Value::from((data > 0.0) as i32 as f64));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Relu),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
+
+    // the subgradient at exactly zero is taken from the negative side
+    pub fn leaky_relu(&self, slope: f64) -> Value {
+        let input = self.data();
+        let data = if input > 0.0 { input } else { slope * input };
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let input = value.previous[0].data();
+            let slope = value.previous[1].data();
+            let local = if input > 0.0 { 1.0 } else { slope };
+
+            accumulate(&value.previous[0], &gradient * &Value::from(local));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::LeakyRelu),
+            vec![self.clone(), Value::constant(slope)],
+            Some(propagate),
+        ))
+    }
+
+    // x for x > 0, else alpha * (exp(x) - 1); the local gradient below zero is
+    // alpha * exp(x), so it is continuous at zero when alpha is 1
+    pub fn elu(&self, alpha: f64) -> Value {
+        let input = self.data();
+        let data = if input > 0.0 { input } else { alpha * input.exp_m1() };
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let (input, alpha) = (&value.previous[0], &value.previous[1]);
+            // built from the input itself, so the second derivative survives too
+            let local = if input.data() > 0.0 { Value::from(1.0) } else { alpha * &input.exp() };
+
+            accumulate(input, &gradient * &local);
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Elu),
+            vec![self.clone(), Value::constant(alpha)],
+            Some(propagate),
+        ))
+    }
+
+    // x * sigmoid(x), also known as swish; composed, so the product rule gives
+    // the gradient sigmoid(x) * (1 + x * (1 - sigmoid(x)))
+    pub fn silu(&self) -> Value {
+        self * &self.sigmoid()
+    }
+
+    // the subgradient at exactly zero is taken to be zero
+    pub fn abs(&self) -> Value {
+        let data = self.data().abs();
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let gradient = value.upstream();
+            let input = value.previous[0].data();
+            let sign = if input == 0.0 { 0.0 } else { input.signum() };
+
+            accumulate(&value.previous[0], &gradient * &Value::from(sign));
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Abs),
+            vec![self.clone()],
+            Some(propagate),
+        ))
+    }
+
+    // the whole gradient goes to the selected operand; ties select self
+    pub fn max(&self, other: &Value) -> Value {
+        let data = self.data().max(other.data());
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let (u, v) = (&value.previous[0], &value.previous[1]);
+            let selected = if u.data() >= v.data() { u } else { v };
+
+            accumulate(selected, value.upstream());
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Max),
+            vec![self.clone(), other.clone()],
+            Some(propagate),
+        ))
+    }
+
+    pub fn min(&self, other: &Value) -> Value {
+        let data = self.data().min(other.data());
+        let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+            let (u, v) = (&value.previous[0], &value.previous[1]);
+            let selected = if u.data() <= v.data() { u } else { v };
+
+            accumulate(selected, value.upstream());
+        };
+
+        Value::new(ValueInternal::new(
+            data,
+            None,
+            Some(Operation::Min),
+            vec![self.clone(), other.clone()],
+            Some(propagate),
+        ))
+    }
+
+    // comparisons are not differentiable, so they yield a detached 1.0 or 0.0
+    pub fn gt(&self, other: &Value) -> Value {
+        Value::from((self.data() > other.data()) as i32)
+    }
+
+    pub fn lt(&self, other: &Value) -> Value {
+        Value::from((self.data() < other.data()) as i32)
+    }
+
+    pub fn ge(&self, other: &Value) -> Value {
+        Value::from((self.data() >= other.data()) as i32)
+    }
+
+    pub fn le(&self, other: &Value) -> Value {
+        Value::from((self.data() <= other.data()) as i32)
+    }
+}
+
+// values are graph nodes, so equality and hashing go by identity: two distinct
+// nodes holding the same data are different, and a node's hash is unaffected
+// by its data or gradient changing. compare data() for numeric equality
+impl Hash for Value {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Shared::as_ptr(&self.internal).hash(state);
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let internal = self.internal.borrow();
+        match &internal.label {
+            Some(label) => write!(
+                f,
+                "Value(label={}, data={:?}, grad={:?})",
+                label, internal.data, internal.gradient()
+            ),
+            None => write!(f, "Value(data={:?}, grad={:?})", internal.data, internal.gradient()),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.same(other)
+    }
+}
+
+impl Eq for Value {}
+
+impl Deref for Value {
+    type Target = Handle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+impl From<f64> for Value {
+    fn from(data: f64) -> Self {
+        Value::new(ValueInternal::new(data, None, None, vec![], None))
+    }
+}
+
+impl From<f32> for Value {
+    fn from(data: f32) -> Self {
+        Value::from(data as f64)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(data: i32) -> Self {
+        Value::from(data as f64)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(data: i64) -> Self {
+        Value::from(data as f64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(data: usize) -> Self {
+        Value::from(data as f64)
+    }
+}
+
+fn add(u: &Value, v: &Value) -> Value {
+    let data = u.data() + v.data();
+    let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+        let gradient = value.upstream();
+
+        accumulate(&value.previous[0], gradient.clone());
+        accumulate(&value.previous[1], gradient);
+    };
+
+    Value::new(ValueInternal::new(
+        data,
+        None,
+        Some(Operation::Add),
+        vec![u.clone(), v.clone()],
+        Some(propagate),
+    ))
+}
+
+impl Add for Value {
+    type Output = Value;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        add(&self, &rhs)
+    }
+}
+
+impl<'b> Add<&'b Value> for &Value {
+    type Output = Value;
+
+    fn add(self, rhs: &'b Value) -> Self::Output {
+        add(self, rhs)
+    }
+}
+
+fn mul(u: &Value, v: &Value) -> Value {
+    let data = u.data() * v.data();
+
+    let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+        let gradient = value.upstream();
+        let (u, v) = (&value.previous[0], &value.previous[1]);
+
+        accumulate(u, &gradient * v);
+        accumulate(v, &gradient * u);
+    };
+
+    Value::new(ValueInternal::new(
+        data,
+        None,
+        Some(Operation::Mul),
+        vec![u.clone(), v.clone()],
+        Some(propagate),
+    ))
+}
+
+impl Mul<Value> for Value {
+    type Output = Value;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        mul(&self, &rhs)
+    }
+}
+
+impl<'b> Mul<&'b Value> for &Value {
+    type Output = Value;
+
+    fn mul(self, rhs: &'b Value) -> Self::Output {
+        mul(self, rhs)
+    }
+}
+
+fn neg(u: &Value) -> Value {
+    let data = -u.data();
+
+    let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+        accumulate(&value.previous[0], neg(&value.upstream()));
+    };
+
+    Value::new(ValueInternal::new(
+        data,
+        None,
+        Some(Operation::Neg),
+        vec![u.clone()],
+        Some(propagate),
+    ))
+}
+
+impl Neg for Value {
+    type Output = Value;
+
+    fn neg(self) -> Self::Output {
+        neg(&self)
+    }
+}
+
+impl Neg for &Value {
+    type Output = Value;
+
+    fn neg(self) -> Self::Output {
+        neg(self)
+    }
+}
+
+fn sub(u: &Value, v: &Value) -> Value {
+    let data = u.data() - v.data();
+
+    let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+        let gradient = value.upstream();
+
+        accumulate(&value.previous[0], gradient.clone());
+        accumulate(&value.previous[1], neg(&gradient));
+    };
+
+    Value::new(ValueInternal::new(
+        data,
+        None,
+        Some(Operation::Sub),
+        vec![u.clone(), v.clone()],
+        Some(propagate),
+    ))
+}
+
+impl Sub for Value {
+    type Output = Value;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        sub(&self, &rhs)
+    }
+}
+
+impl<'b> Sub<&'b Value> for &Value {
+    type Output = Value;
+
+    fn sub(self, rhs: &'b Value) -> Self::Output {
+        sub(self, rhs)
+    }
+}
+
+// the compound assignments rebind self to a new result node; the previous node
+// is not mutated and stays in the graph as an operand of the new one
+impl<'b> AddAssign<&'b Value> for Value {
+    fn add_assign(&mut self, rhs: &'b Value) {
+        *self = add(self, rhs);
+    }
+}
+
+impl<'b> SubAssign<&'b Value> for Value {
+    fn sub_assign(&mut self, rhs: &'b Value) {
+        *self = sub(self, rhs);
+    }
+}
+
+impl<'b> MulAssign<&'b Value> for Value {
+    fn mul_assign(&mut self, rhs: &'b Value) {
+        *self = mul(self, rhs);
+    }
+}
+
+// folds chain through add and mul, so every element stays in the graph
+impl Sum for Value {
+    fn sum<I: Iterator<Item = Value>>(iter: I) -> Value {
+        iter.reduce(|acc, v| add(&acc, &v)).unwrap_or_else(|| Value::from(0.0))
+    }
+}
+
+impl<'a> Sum<&'a Value> for Value {
+    fn sum<I: Iterator<Item = &'a Value>>(iter: I) -> Value {
+        iter.cloned().sum()
+    }
+}
+
+impl Product for Value {
+    fn product<I: Iterator<Item = Value>>(iter: I) -> Value {
+        iter.reduce(|acc, v| mul(&acc, &v)).unwrap_or_else(|| Value::from(1.0))
+    }
+}
+
+impl<'a> Product<&'a Value> for Value {
+    fn product<I: Iterator<Item = &'a Value>>(iter: I) -> Value {
+        iter.cloned().product()
+    }
+}
+
+// division by zero follows IEEE 754, yielding an infinite or NaN value and gradient
+fn div(u: &Value, v: &Value) -> Value {
+    let data = u.data() / v.data();
+
+    let propagate: BackPropagteFn = |value: &ReadGuard<ValueInternal>| {
+        let gradient = value.upstream();
+        let (u, v) = (&value.previous[0], &value.previous[1]);
+
+        accumulate(u, &gradient / v);
+        accumulate(v, neg(&(&(&gradient * u) / &(v * v))));
+    };
+
+    Value::new(ValueInternal::new(
+        data,
+        None,
+        Some(Operation::Div),
+        vec![u.clone(), v.clone()],
+        Some(propagate),
+    ))
+}
+
+impl Div for Value {
+    type Output = Value;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        div(&self, &rhs)
+    }
+}
+
+impl<'b> Div<&'b Value> for &Value {
+    type Output = Value;
+
+    fn div(self, rhs: &'b Value) -> Self::Output {
+        div(self, rhs)
+    }
+}
+
+// the graph is flattened into topological order with operands referenced by
+// index, so a node shared by several results is written (and restored) once
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedNode {
+    data: f64,
+    gradient: f64,
+    requires_grad: bool,
+    label: Option<String>,
+    operation: Option<Operation>,
+    previous: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let topo = self.topo_order();
+        let ids: HashMap<_, _> = topo
+            .iter()
+            .enumerate()
+            .map(|(id, value)| (Shared::as_ptr(&value.internal), id))
+            .collect();
+
+        let nodes: Vec<SerializedNode> = topo
+            .iter()
+            .map(|value| {
+                let internal = value.internal.borrow();
+
+                SerializedNode {
+                    data: internal.data,
+                    gradient: internal.gradient(),
+                    requires_grad: internal.requires_grad,
+                    label: internal.label.clone(),
+                    // a result computed under no_grad has no operands to replay,
+                    // so it is written as the leaf it effectively is
+                    operation: if internal.previous.is_empty() { None } else { internal.operation.clone() },
+                    previous: internal
+                        .previous
+                        .iter()
+                        .map(|previous| ids[&Shared::as_ptr(&previous.internal)])
+                        .collect(),
+                }
+            })
+            .collect();
+
+        nodes.serialize(serializer)
+    }
+}
+
+// the root is the last node; backward functions are not serializable, so each
+// operation is replayed on its restored operands to recover its propagate
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Value, D::Error> {
+        use serde::de::Error;
+
+        let nodes = Vec::<SerializedNode>::deserialize(deserializer)?;
+        let mut values: Vec<Value> = Vec::with_capacity(nodes.len());
+
+        for (id, node) in nodes.into_iter().enumerate() {
+            if let Some(&bad) = node.previous.iter().find(|&&previous| previous >= id) {
+                return Err(D::Error::custom(format!("node {} refers to later node {}", id, bad)));
+            }
+            let previous: Vec<Value> = node.previous.iter().map(|&previous| values[previous].clone()).collect();
+
+            // older files wrote no_grad results with their operation but no
+            // operands; those are read back as the leaves they are
+            let operation = if previous.is_empty() { None } else { node.operation };
+            let propagate = match &operation {
+                None if previous.is_empty() => None,
+                None => return Err(D::Error::custom(format!("leaf node {} has operands", id))),
+                Some(operation) => {
+                    let replayed = match replay(operation, &previous) {
+                        Some(replayed) => replayed,
+                        None => {
+                            return Err(D::Error::custom(format!(
+                                "node {} has {} operands for {:?}",
+                                id,
+                                previous.len(),
+                                operation
+                            )))
+                        }
+                    };
+                    let propagate = replayed.internal.borrow().propagate;
+                    propagate
+                }
+            };
+
+            let mut internal = ValueInternal::new(node.data, node.label, operation, previous, propagate);
+            internal.gradient = Some(Value::from(node.gradient));
+            internal.requires_grad = node.requires_grad;
+            values.push(Value::new(internal));
+        }
+
+        values.pop().ok_or_else(|| D::Error::custom("a value graph needs at least one node"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backward_square() {
+        let v1 = Value::from(1.5);
+        let v2 = Value::from(4.0);
+        let v3 = &(&v1 * &v1) * &v2;
+
+        v3.backward();
+
+        assert_eq!(v1.gradient(), 12.0);
+        assert_eq!(v2.gradient(), 2.25);
+        assert_eq!(v3.gradient(), 1.0);
+    }
+
+    #[test]
+    fn test_backward_diamond() {
+        let a = Value::from(2.0);
+        let b = &a + &a;
+        let c = &a * &a;
+        let d = &b * &c;
+
+        d.backward();
+
+        assert_eq!(d.data(), 16.0);
+        assert_eq!(b.gradient(), 4.0);
+        assert_eq!(c.gradient(), 4.0);
+        assert_eq!(a.gradient(), 24.0);
+    }
+
+    #[test]
+    fn test_backward_shared_intermediate() {
+        let a = Value::from(3.0);
+        let b = Value::from(-2.0);
+        let c = &a * &b;
+        let d = &c + &a;
+        let e = &c * &d;
+
+        e.backward();
+
+        // c reaches e both directly and through d
+        assert_eq!(e.data(), 18.0);
+        assert_eq!(d.gradient(), -6.0);
+        assert_eq!(c.gradient(), -9.0);
+        assert_eq!(a.gradient(), 12.0);
+        assert_eq!(b.gradient(), -27.0);
+    }
+
+    #[test]
+    fn test_div() {
+        let a = Value::from(3.0);
+        let b = Value::from(4.0);
+        let c = &a / &b;
+
+        c.backward();
+
+        assert_eq!(c.data(), 0.75);
+        assert_eq!(a.gradient(), 0.25);
+        assert_eq!(b.gradient(), -3.0 / 16.0);
+    }
+
+    #[test]
+    fn test_div_shared_operand() {
+        let a = Value::from(2.0);
+        let b = &a / &(&a * &a);
+
+        b.backward();
+
+        assert_eq!(b.data(), 0.5);
+        assert_eq!(a.gradient(), -0.25);
+    }
+
+    #[test]
+    fn test_div_by_zero() {
+        let a = Value::from(1.0);
+        let b = Value::from(0.0);
+        assert_eq!((&a / &b).data(), f64::INFINITY);
+        assert_eq!((-&a / b.clone()).data(), f64::NEG_INFINITY);
+        assert!((&b / &b).data().is_nan());
+    }
+
+    #[test]
+    fn test_powf_integer() {
+        let x = Value::from(3.0);
+        let y = x.powf(2.0);
+
+        y.backward();
+
+        assert_eq!(y.data(), 9.0);
+        assert_eq!(x.gradient(), 6.0);
+    }
+
+    #[test]
+    fn test_powf_fractional() {
+        let x = Value::from(4.0);
+        let y = x.powf(0.5);
+
+        y.backward();
+
+        assert_eq!(y.data(), 2.0);
+        assert_eq!(x.gradient(), 0.25);
+    }
+
+    #[test]
+    fn test_powf_negative() {
+        let x = Value::from(2.0);
+        let y = x.powf(-1.0);
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.5);
+        assert_eq!(x.gradient(), -0.25);
+    }
+
+    #[test]
+    fn test_powi() {
+        let x = Value::from(3.0);
+
+        let zero = x.powi(0);
+        zero.backward();
+
+        assert_eq!(zero.data(), 1.0);
+        assert_eq!(x.gradient(), 0.0);
+
+        let cube = x.powi(3);
+        cube.backward();
+
+        assert_eq!(cube.data(), 27.0);
+        assert_eq!(x.gradient(), 27.0);
+
+        x.zero_grad();
+        let inverse_square = x.powi(-2);
+        inverse_square.backward();
+
+        assert!((inverse_square.data() - 1.0 / 9.0).abs() < 1e-15);
+        assert!((x.gradient() - -2.0 / 27.0).abs() < 1e-15);
+
+        x.zero_grad();
+        let seventh = x.powi(7);
+        seventh.backward();
+
+        assert_eq!(seventh.data(), 2187.0);
+        assert_eq!(x.gradient(), 7.0 * 729.0);
+    }
+
+    #[test]
+    fn test_powv() {
+        let x = Value::from(2.0);
+        let n = Value::from(3.0);
+        let y = x.powv(&n);
+
+        y.backward();
+
+        assert_eq!(y.data(), 8.0);
+        assert_eq!(x.gradient(), 12.0);
+        assert!((n.gradient() - 8.0 * 2.0_f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_pow_alias() {
+        let x = Value::from(2.0);
+        let n = Value::from(3.0);
+        let y = x.pow(&n);
+
+        y.backward();
+
+        assert_eq!(y.data(), 8.0);
+        assert_eq!(x.gradient(), 12.0);
+        assert_eq!(y.borrow().operation, Some(Operation::Pow));
+    }
+
+    #[test]
+    fn test_exp() {
+        let x = Value::from(0.0);
+        let y = x.exp();
+
+        y.backward();
+
+        assert_eq!(y.data(), 1.0);
+        assert_eq!(x.gradient(), 1.0);
+    }
+
+    #[test]
+    fn test_ln() {
+        let x = Value::from(4.0);
+        let y = x.ln();
+
+        y.backward();
+
+        assert_eq!(y.data(), 4.0_f64.ln());
+        assert_eq!(x.gradient(), 0.25);
+    }
+
+    #[test]
+    fn test_exp_ln_round_trip() {
+        let x = Value::from(1.7);
+        let y = x.exp().ln();
+
+        y.backward();
+
+        assert!((y.data() - 1.7).abs() < 1e-12);
+        assert!((x.gradient() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let x = Value::from(0.1);
+        let y = &(&x + &Value::from(0.2)) * &Value::from(3.0);
+
+        assert_ne!(y.data(), 0.9);
+        assert!(y.approx_eq(0.9, 1e-9));
+        assert!(!y.approx_eq(0.91, 1e-9));
+        assert!(y.approx_eq(1.0, 0.1));
+    }
+
+    #[test]
+    fn test_tanh_neuron() {
+        let x1 = Value::from(2.0);
+        let x2 = Value::from(0.0);
+        let w1 = Value::from(-3.0);
+        let w2 = Value::from(1.0);
+        let b = Value::from(6.881_373_587_019_543);
+
+        let n = &(&(&x1 * &w1) + &(&x2 * &w2)) + &b;
+        let o = n.tanh();
+
+        o.backward();
+
+        assert!(o.approx_eq(std::f64::consts::FRAC_1_SQRT_2, 1e-9));
+        assert!((n.gradient() - 0.5).abs() < 1e-9);
+        assert!((x1.gradient() - -1.5).abs() < 1e-9);
+        assert!((w1.gradient() - 1.0).abs() < 1e-9);
+        assert!((x2.gradient() - 0.5).abs() < 1e-9);
+        assert!(w2.gradient().abs() < 1e-9);
+        assert_eq!(o.borrow().operation, Some(Operation::Tanh));
+    }
+
+    #[test]
+    fn test_relu() {
+        let pos = Value::from(2.0);
+        let neg = Value::from(-2.0);
+        let y = &pos.relu() + &neg.relu();
+
+        y.backward();
+
+        assert_eq!(y.data(), 2.0);
+        assert_eq!(pos.gradient(), 1.0);
+        assert_eq!(neg.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_relu_at_zero() {
+        let x = Value::from(0.0);
+        let y = x.relu();
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.0);
+        assert_eq!(x.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_leaky_relu() {
+        let pos = Value::from(2.0);
+        let neg = Value::from(-2.0);
+        let y = &pos.leaky_relu(0.1) + &neg.leaky_relu(0.1);
+
+        y.backward();
+
+        assert_eq!(y.data(), 1.8);
+        assert_eq!(pos.gradient(), 1.0);
+        assert_eq!(neg.gradient(), 0.1);
+    }
+
+    #[test]
+    fn test_leaky_relu_at_zero() {
+        let x = Value::from(0.0);
+        let y = x.leaky_relu(0.1);
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.0);
+        assert_eq!(x.gradient(), 0.1);
+    }
+
+    #[test]
+    fn test_sigmoid() {
+        let x = Value::from(0.0);
+        let y = x.sigmoid();
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.5);
+        assert_eq!(x.gradient(), 0.25);
+    }
+
+    #[test]
+    fn test_sigmoid_large_magnitude() {
+        let pos = Value::from(1000.0);
+        let neg = Value::from(-1000.0);
+        let y = &pos.sigmoid() + &neg.sigmoid();
+
+        y.backward();
+
+        assert_eq!(pos.sigmoid().data(), 1.0);
+        assert_eq!(neg.sigmoid().data(), 0.0);
+        assert_eq!(pos.gradient(), 0.0);
+        assert_eq!(neg.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_elu() {
+        let pos = Value::from(2.0);
+        let neg = Value::from(-1.0);
+        let y = &pos.elu(0.5) + &neg.elu(0.5);
+
+        y.backward();
+
+        assert_eq!(pos.elu(0.5).data(), 2.0);
+        assert!(neg.elu(0.5).approx_eq(0.5 * ((-1.0_f64).exp() - 1.0), 1e-15));
+        assert_eq!(pos.gradient(), 1.0);
+        assert!((neg.gradient() - 0.5 * (-1.0_f64).exp()).abs() < 1e-15);
+        assert_eq!(pos.elu(0.5).borrow().operation, Some(Operation::Elu));
+    }
+
+    #[test]
+    fn test_silu() {
+        let x = Value::from(0.0);
+        let y = x.silu();
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.0);
+        assert_eq!(x.gradient(), 0.5);
+        assert!(Value::from(2.0).silu().approx_eq(2.0 / (1.0 + (-2.0_f64).exp()), 1e-15));
+    }
+
+    #[test]
+    fn test_gelu() {
+        let x = Value::from(0.0);
+        let y = x.gelu();
+
+        y.backward();
+
+        assert_eq!(y.data(), 0.0);
+        assert_eq!(x.gradient(), 0.5);
+        assert!(Value::from(1.0).gelu().approx_eq(0.841_191_990_608_276_8, 1e-12));
+        assert!(Value::from(-3.0).gelu().approx_eq(-0.003_637_392_081_772_994_3, 1e-12));
     }
-}
 
-impl Sub for Value {
-    type Output = Value;
+    #[test]
+    fn test_softplus() {
+        let x = Value::from(0.0);
+        let y = x.softplus();
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        add(&self, &(-rhs))
+        y.backward();
+
+        assert!(y.approx_eq(2.0_f64.ln(), 1e-15));
+        assert_eq!(x.gradient(), 0.5);
+        assert_eq!(y.borrow().operation, Some(Operation::Softplus));
     }
-}
 
-impl<'a, 'b> Sub<&'b Value> for &'a Value {
-    type Output = Value;
+    #[test]
+    fn test_softplus_large_magnitude() {
+        let pos = Value::from(1000.0);
+        let neg = Value::from(-1000.0);
+        let small = Value::from(-30.0);
+        let y = &(&pos.softplus() + &neg.softplus()) + &small.softplus();
 
-    fn sub(self, rhs: &'b Value) -> Self::Output {
-        add(self, &(-rhs))
+        y.backward();
+
+        assert_eq!(pos.softplus().data(), 1000.0);
+        assert_eq!(neg.softplus().data(), 0.0);
+        assert!((small.softplus().data() - (-30.0_f64).exp()).abs() < 1e-25);
+        assert_eq!(pos.gradient(), 1.0);
+        assert_eq!(neg.gradient(), 0.0);
+        assert!((small.gradient() - (-30.0_f64).exp()).abs() < 1e-25);
+    }
+
+    #[test]
+    fn test_label() {
+        let v = Value::from(1.0);
+        assert_eq!(v.label(), None);
+
+        let alias = v.clone();
+        v.set_label("weight");
+
+        assert_eq!(v.label(), Some("weight".to_string()));
+        assert_eq!(alias.label(), Some("weight".to_string()));
+        assert_eq!(Value::from(2.0).set_label("bias").label(), Some("bias".to_string()));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let a = Value::from(2.0);
+        let b = Value::from(-3.0);
+        let c = Value::from(10.0);
+        c.set_label("c");
+        let d = &(&a * &b) + &c;
+
+        let dot = d.to_dot();
+
+        assert!(dot.starts_with("digraph {"));
+        assert_eq!(dot.matches("shape=record").count(), 5);
+        assert_eq!(dot.matches("shape=oval").count(), 2);
+        assert_eq!(dot.matches("->").count(), 6);
+        assert!(dot.contains("label=\"Mul\""));
+        assert!(dot.contains("label=\"Add\""));
+        assert!(dot.contains("{ c | data 10.0000 | grad 0.0000 }"));
+    }
+
+    #[test]
+    fn test_display() {
+        let v = Value::from(1.2);
+        assert_eq!(v.to_string(), "Value(data=1.2, grad=0.0)");
+
+        v.set_label("w");
+        v.backward();
+        assert_eq!(format!("{}", v), "Value(label=w, data=1.2, grad=1.0)");
+    }
+
+    #[test]
+    fn test_detach() {
+        let x = Value::from(2.0);
+        let w = Value::from(3.0);
+        let y = &x.detach() * &w;
+
+        y.backward();
+
+        assert_eq!(y.data(), 6.0);
+        assert_eq!(x.gradient(), 0.0);
+        assert_eq!(w.gradient(), 2.0);
+    }
+
+    #[test]
+    fn test_recompute() {
+        let x = Value::from(2.0);
+        let w = Value::from(3.0);
+        let y = (&(&x * &w) + &x.powf(2.0)).tanh();
+        let z = &y * &x;
+
+        x.set_data(-1.0);
+        z.recompute();
+
+        let expected = (-3.0_f64 + 1.0).tanh();
+        assert!(y.approx_eq(expected, 1e-12));
+        assert!(z.approx_eq(-expected, 1e-12));
+        assert_eq!(z.graph_stats().node_count, 8);
+
+        z.backward();
+
+        // dz/dw = x^2 * (1 - y^2), with x back at -1
+        assert!((w.gradient() - (1.0 - expected * expected)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_recompute_with_no_grad_node() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let x = Value::from(4.0);
+        let c = {
+            let _guard = no_grad();
+            &a * &b
+        };
+        let d = &c * &x;
+
+        x.set_data(5.0);
+        a.set_data(10.0);
+        d.recompute();
+
+        // c recorded no graph, so it keeps its value and only x's change shows
+        assert_eq!(c.data(), 6.0);
+        assert_eq!(d.data(), 30.0);
+    }
+
+    #[test]
+    fn test_detach_graph() {
+        let a = Value::from(2.0);
+        let b = &a * &Value::from(3.0);
+        let c = b.tanh();
+
+        assert_eq!(Shared::strong_count(&a.internal), 2);
+        assert_eq!(Shared::strong_count(&b.internal), 2);
+
+        c.backward();
+        let gradient = c.gradient();
+        c.detach_graph();
+
+        assert_eq!(Shared::strong_count(&b.internal), 1);
+        drop(b);
+        assert_eq!(Shared::strong_count(&a.internal), 1);
+
+        assert_eq!(c.data(), 6.0_f64.tanh());
+        assert_eq!(c.gradient(), gradient);
+        assert_eq!(c.graph_stats().node_count, 1);
+
+        a.zero_grad();
+        (&c * &c).backward();
+        assert_eq!(a.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_backward_retain() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let c = &a * &b;
+        let d = c.tanh();
+
+        d.backward_retain(true);
+        let gradient = a.gradient();
+        a.zero_grad();
+        d.backward_retain(true);
+
+        assert_eq!(a.gradient(), gradient);
+
+        a.zero_grad();
+        d.backward_retain(false);
+
+        assert_eq!(a.gradient(), gradient);
+        assert_eq!(Shared::strong_count(&a.internal), 1);
+        assert_eq!(c.graph_stats().node_count, 1);
+        assert_eq!(c.data(), 6.0);
+
+        // the graph is gone, so nothing reaches the leaves any more
+        a.zero_grad();
+        d.backward();
+
+        assert_eq!(a.gradient(), 0.0);
+        assert_eq!(d.gradient(), 1.0);
+    }
+
+    #[test]
+    fn test_leaf() {
+        let w = Value::leaf(0.5, "w");
+
+        assert_eq!(w.data(), 0.5);
+        assert_eq!(w.label(), Some("w".to_string()));
+        assert!(w.requires_grad());
+    }
+
+    #[test]
+    fn test_requires_grad_false() {
+        let frozen = Value::leaf(2.0, "frozen");
+        frozen.set_requires_grad(false);
+        let w = Value::leaf(3.0, "w");
+
+        let y = &(&frozen * &w) + &frozen;
+        y.backward();
+
+        assert_eq!(y.data(), 8.0);
+        assert_eq!(frozen.gradient(), 0.0);
+        assert_eq!(w.gradient(), 2.0);
+    }
+
+    #[test]
+    fn test_no_grad() {
+        let x = Value::from(2.0);
+        let y = Value::from(3.0);
+
+        let z = {
+            let _guard = no_grad();
+            &x * &y
+        };
+        z.backward();
+
+        assert_eq!(z.data(), 6.0);
+        assert!(z.borrow().previous.is_empty());
+        assert_eq!(z.borrow().operation, None);
+        assert_eq!(x.gradient(), 0.0);
+        assert_eq!(y.gradient(), 0.0);
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn test_no_grad_nested() {
+        let outer = no_grad();
+        {
+            let _inner = no_grad();
+            assert!(!is_grad_enabled());
+        }
+        assert!(!is_grad_enabled());
+
+        drop(outer);
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn test_no_grad_restored_on_panic() {
+        let result = std::panic::catch_unwind(|| {
+            let _guard = no_grad();
+            panic!("inside no_grad");
+        });
+
+        assert!(result.is_err());
+        assert!(is_grad_enabled());
+    }
+
+    #[test]
+    fn test_zero_grad_graph() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let c = &a * &b;
+        let d = &c + &a;
+
+        d.backward();
+        assert_ne!(a.gradient(), 0.0);
+
+        d.zero_grad_graph();
+
+        for value in [&a, &b, &c, &d] {
+            assert_eq!(value.gradient(), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_backward_deep_chain() {
+        let x = Value::from(1.0);
+        let mut acc = x.clone();
+        for _ in 0..100_000 {
+            acc = &acc + &x;
+        }
+
+        acc.backward();
+
+        assert_eq!(acc.data(), 100_001.0);
+        assert_eq!(x.gradient(), 100_001.0);
+    }
+
+    #[test]
+    fn test_backward_accumulate() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let c = &(&a * &b) + &a;
+
+        c.backward_accumulate(1.0);
+        assert_eq!(a.gradient(), 4.0);
+        assert_eq!(b.gradient(), 2.0);
+
+        c.backward_accumulate(1.0);
+        assert_eq!(a.gradient(), 8.0);
+        assert_eq!(b.gradient(), 4.0);
+
+        c.zero_grad_graph();
+        c.backward_accumulate(0.5);
+        assert_eq!(a.gradient(), 2.0);
+        assert_eq!(b.gradient(), 1.0);
+    }
+
+    #[test]
+    fn test_sub_operation() {
+        let a = Value::from(5.0);
+        let b = Value::from(2.0);
+        let c = &a - &b;
+
+        c.backward();
+
+        assert_eq!(c.data(), 3.0);
+        assert_eq!(c.borrow().operation, Some(Operation::Sub));
+        assert!(c.borrow().previous[0].same(&a));
+        assert!(c.borrow().previous[1].same(&b));
+        assert_eq!(a.gradient(), 1.0);
+        assert_eq!(b.gradient(), -1.0);
+    }
+
+    #[test]
+    fn test_neg_operation() {
+        let a = Value::from(5.0);
+        let b = -&a;
+
+        b.backward();
+
+        assert_eq!(b.data(), -5.0);
+        assert_eq!(b.borrow().operation, Some(Operation::Neg));
+        assert_eq!(a.gradient(), -1.0);
+    }
+
+    #[test]
+    fn test_operation_tags() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+
+        assert_eq!((&a + &b).borrow().operation, Some(Operation::Add));
+        assert_eq!((&a * &b).borrow().operation, Some(Operation::Mul));
+        assert_eq!((&a / &b).borrow().operation, Some(Operation::Div));
+        assert_eq!(a.powf(2.0).borrow().operation, Some(Operation::Powf));
+        assert_eq!(a.powv(&b).borrow().operation, Some(Operation::Pow));
+        assert_eq!(a.exp().borrow().operation, Some(Operation::Exp));
+        assert_eq!(a.ln().borrow().operation, Some(Operation::Ln));
+        assert_eq!(a.relu().borrow().operation, Some(Operation::Relu));
+        assert_eq!(a.sigmoid().borrow().operation, Some(Operation::Sigmoid));
+        assert_eq!(a.borrow().operation, None);
+    }
+
+    #[test]
+    fn test_from_numeric_types() {
+        assert_eq!(Value::from(1.5_f32).data(), 1.5);
+        assert_eq!(Value::from(3).data(), 3.0);
+        assert_eq!(Value::from(-4_i64).data(), -4.0);
+        assert_eq!(Value::from(7_usize).data(), 7.0);
+    }
+
+    #[test]
+    fn test_sum() {
+        let values: Vec<Value> = (1..=4).map(Value::from).collect();
+
+        let total = values.iter().cloned().sum::<Value>();
+        total.backward();
+
+        assert_eq!(total.data(), 10.0);
+        for v in values.iter() {
+            assert_eq!(v.gradient(), 1.0);
+        }
+        assert_eq!(values.iter().sum::<Value>().data(), 10.0);
+        assert_eq!(Vec::<Value>::new().into_iter().sum::<Value>().data(), 0.0);
+    }
+
+    #[test]
+    fn test_product() {
+        let values: Vec<Value> = (1..=4).map(Value::from).collect();
+
+        let total = values.iter().product::<Value>();
+        total.backward();
+
+        assert_eq!(total.data(), 24.0);
+        assert_eq!(
+            values.iter().map(|v| v.gradient()).collect::<Vec<f64>>(),
+            vec![24.0, 12.0, 8.0, 6.0]
+        );
+        assert_eq!(Vec::<Value>::new().into_iter().product::<Value>().data(), 1.0);
+    }
+
+    #[test]
+    fn test_add_assign_loop() {
+        let x = Value::from(2.0);
+        let start = Value::from(1.0);
+
+        let mut acc = start.clone();
+        for _ in 0..3 {
+            acc += &x;
+        }
+        acc.backward();
+
+        assert_eq!(acc.data(), 7.0);
+        assert_eq!(x.gradient(), 3.0);
+        assert_eq!(start.gradient(), 1.0);
+        assert_eq!(start.data(), 1.0);
+    }
+
+    #[test]
+    fn test_sub_mul_assign() {
+        let x = Value::from(3.0);
+
+        let mut acc = Value::from(1.0);
+        acc *= &x;
+        acc *= &x;
+        acc -= &x;
+        acc.backward();
+
+        assert_eq!(acc.data(), 6.0);
+        assert_eq!(x.gradient(), 5.0);
+    }
+
+    #[test]
+    fn test_max() {
+        let a = Value::from(2.0);
+        let b = Value::from(5.0);
+        let c = a.max(&b);
+
+        (&c * &Value::from(3.0)).backward();
+
+        assert_eq!(c.data(), 5.0);
+        assert_eq!(a.gradient(), 0.0);
+        assert_eq!(b.gradient(), 3.0);
+    }
+
+    #[test]
+    fn test_min() {
+        let a = Value::from(2.0);
+        let b = Value::from(5.0);
+        let c = a.min(&b);
+
+        c.backward();
+
+        assert_eq!(c.data(), 2.0);
+        assert_eq!(a.gradient(), 1.0);
+        assert_eq!(b.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_max_tie_routes_to_first() {
+        let a = Value::from(1.0);
+        let b = Value::from(1.0);
+
+        a.max(&b).backward();
+
+        assert_eq!(a.gradient(), 1.0);
+        assert_eq!(b.gradient(), 0.0);
+    }
+
+    #[test]
+    fn test_sqrt_recip_exp2() {
+        let x = Value::from(4.0);
+
+        assert_eq!(x.sqrt().data(), 2.0);
+        assert_eq!(x.recip().data(), 0.25);
+        assert_eq!(x.exp2().data(), 16.0);
+
+        x.sqrt().backward();
+        assert_eq!(x.gradient(), 0.25);
+
+        x.zero_grad();
+        x.recip().backward();
+        assert_eq!(x.gradient(), -1.0 / 16.0);
+
+        x.zero_grad();
+        x.exp2().backward();
+        assert!((x.gradient() - 16.0 * std::f64::consts::LN_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sin_cos() {
+        let x = Value::from(0.0);
+
+        assert_eq!(x.sin().data(), 0.0);
+        assert_eq!(x.cos().data(), 1.0);
+
+        x.sin().backward();
+        assert_eq!(x.gradient(), 1.0);
+
+        x.zero_grad();
+        x.cos().backward();
+        assert_eq!(x.gradient(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "anomaly detected: Ln node (label Some(\"log\"))")]
+    fn test_detect_anomaly() {
+        set_detect_anomaly(true);
+
+        let x = Value::from(0.0);
+        let y = x.ln();
+        y.set_label("log");
+
+        y.backward();
+    }
+
+    #[test]
+    fn test_detect_anomaly_finite_graph() {
+        set_detect_anomaly(true);
+
+        let x = Value::from(0.5);
+        (&x.ln() * &x).backward();
+
+        set_detect_anomaly(false);
+        assert!(!is_anomaly_enabled());
+        assert!((x.gradient() - (0.5_f64.ln() + 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_topo_order() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let c = &a * &b;
+        let d = &c + &a;
+        let e = d.tanh();
+
+        let order = e.topo_order();
+        let position = |v: &Value| order.iter().position(|o| o == v).unwrap();
+
+        assert_eq!(order.len(), 5);
+        assert_eq!(order.last(), Some(&e));
+        assert!(position(&a) < position(&c) && position(&b) < position(&c));
+        assert!(position(&c) < position(&d) && position(&d) < position(&e));
+        assert_eq!(e.topo_order(), order);
+    }
+
+    #[test]
+    fn test_graph_stats() {
+        let a = Value::from(2.0);
+        let b = Value::from(3.0);
+        let c = &a * &b;
+        let d = &c + &a;
+        let e = d.tanh();
+
+        let stats = e.graph_stats();
+
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.edge_count, 5);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(
+            stats.operations,
+            HashMap::from([(Operation::Mul, 1), (Operation::Add, 1), (Operation::Tanh, 1)])
+        );
+        assert_eq!(a.graph_stats().max_depth, 0);
+    }
+
+    #[test]
+    fn test_backward_into() {
+        let a = Value::from(2.0);
+        let b = Value::from(2.0);
+        let frozen = Value::from(5.0);
+        frozen.set_requires_grad(false);
+        let c = &(&a * &b) + &(&a * &frozen);
+
+        let gradients = c.backward_into();
+
+        assert_eq!(gradients.len(), 2);
+        assert_eq!(gradients[&a], 7.0);
+        assert_eq!(gradients[&b], 2.0);
+        assert!(!gradients.contains_key(&frozen));
+    }
+
+    #[test]
+    fn test_backward_into_skips_op_constants() {
+        let x = Value::from(1.5);
+        let y = &(&(&x.powf(2.0) + &x.leaky_relu(0.1)) + &x.elu(1.0)) + &x.gelu();
+
+        let gradients = y.backward_into();
+
+        assert_eq!(gradients.len(), 1);
+        assert_eq!(gradients[&x], x.gradient());
+    }
+
+    #[test]
+    fn test_identity_eq_hash() {
+        let a = Value::from(1.0);
+        let b = Value::from(1.0);
+        let c = a.clone();
+
+        assert_ne!(a, b);
+        assert_eq!(a, c);
+        assert!(a.same(&c));
+        assert!(!a.same(&b));
+        assert!(!a.same(&a.detach()));
+
+        let mut nodes = HashSet::new();
+        nodes.insert(a.clone());
+        nodes.insert(b.clone());
+        nodes.insert(c.clone());
+        assert_eq!(nodes.len(), 2);
+
+        // mutating a node's gradient does not move it within the set
+        (&a * &b).backward();
+        assert!(nodes.contains(&a));
+        assert!(nodes.contains(&b));
+    }
+
+    #[test]
+    fn test_step() {
+        let x = Value::from(0.0);
+
+        let mut previous = x.data();
+        for _ in 0..20 {
+            (&x - &Value::from(5.0)).powf(2.0).backward();
+            x.step(0.1);
+            x.zero_grad();
+
+            assert!(x.data() > previous && x.data() < 5.0);
+            previous = x.data();
+        }
+
+        assert!((x.data() - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_abs() {
+        for (input, gradient) in [(2.5, 1.0), (-2.5, -1.0), (0.0, 0.0)] {
+            let x = Value::from(input);
+            let y = x.abs();
+
+            (&y * &Value::from(3.0)).backward();
+
+            assert_eq!(y.data(), f64::abs(input));
+            assert_eq!(x.gradient(), 3.0 * gradient);
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let a = Value::from(1.0);
+        let b = Value::from(2.0);
+
+        assert_eq!(a.gt(&b).data(), 0.0);
+        assert_eq!(a.lt(&b).data(), 1.0);
+        assert_eq!(a.ge(&b).data(), 0.0);
+        assert_eq!(a.le(&b).data(), 1.0);
+        assert_eq!(a.ge(&a).data(), 1.0);
+        assert_eq!(a.gt(&a).data(), 0.0);
+    }
+
+    #[test]
+    fn test_comparison_mask_has_no_gradient() {
+        let a = Value::from(3.0);
+        let b = Value::from(2.0);
+        let mask = a.gt(&b);
+
+        let y = &mask * &a;
+        y.backward();
+
+        assert_eq!(y.data(), 3.0);
+        assert_eq!(a.gradient(), 1.0);
+        assert_eq!(b.gradient(), 0.0);
+        assert!(mask.internal.borrow().previous.is_empty());
+    }
+
+    #[test]
+    fn test_second_derivative_cube() {
+        let x = Value::from(2.0);
+        let y = x.powf(3.0);
+
+        y.backward_create_graph();
+        let dy_dx = x.gradient_value();
+        x.zero_grad();
+        dy_dx.backward();
+
+        assert_eq!(dy_dx.data(), 12.0);
+        assert_eq!(x.gradient(), 12.0);
+    }
+
+    #[test]
+    fn test_second_derivative_mixed() {
+        let x = Value::from(0.5);
+        let y = Value::from(-1.5);
+        let f = &(&x * &y).tanh() + &(&x * &x);
+
+        f.backward_create_graph();
+        let df_dx = x.gradient_value();
+        x.zero_grad();
+        y.zero_grad();
+        df_dx.backward();
+
+        // d/dx (y * sech^2(xy) + 2x) and d/dy of the same
+        let t = (0.5_f64 * -1.5).tanh();
+        let sech2 = 1.0 - t * t;
+        assert!((x.gradient() - (-2.0 * -1.5 * -1.5 * t * sech2 + 2.0)).abs() < 1e-12);
+        assert!((y.gradient() - (sech2 - 2.0 * 0.5 * -1.5 * t * sech2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_backward_records_no_graph() {
+        let x = Value::from(2.0);
+
+        x.powf(3.0).backward();
+
+        assert_eq!(x.gradient(), 12.0);
+        assert!(x.gradient_value().internal.borrow().previous.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let a = Value::from(3.0);
+        let b = Value::from(-2.0);
+        a.set_label("a");
+        let c = &(&a * &b) + &a;
+
+        let json = serde_json::to_string(&c).unwrap();
+        let restored: Value = serde_json::from_str(&json).unwrap();
+
+        let product = restored.internal.borrow().previous[0].clone();
+        let a = restored.internal.borrow().previous[1].clone();
+        let b = product.internal.borrow().previous[1].clone();
+        assert!(product.internal.borrow().previous[0].same(&a));
+        assert_eq!(restored.data(), -3.0);
+        assert_eq!(a.label(), Some("a".to_string()));
+        assert_eq!(product.internal.borrow().operation, Some(Operation::Mul));
+
+        restored.backward();
+
+        // a reaches the root both through the product and directly
+        assert_eq!(a.gradient(), -1.0);
+        assert_eq!(b.gradient(), 3.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_preserves_gradient() {
+        let x = Value::from(0.5);
+        let y = x.tanh();
+        y.backward();
+
+        let restored: Value = serde_json::from_str(&serde_json::to_string(&y).unwrap()).unwrap();
+
+        assert_eq!(restored.gradient(), 1.0);
+        assert_eq!(restored.internal.borrow().previous[0].gradient(), x.gradient());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_no_grad_node() {
+        let a = Value::from(2.0);
+        let c = {
+            let _guard = no_grad();
+            &a * &a
+        };
+        let root = &c * &Value::from(5.0);
+
+        let restored: Value = serde_json::from_str(&serde_json::to_string(&root).unwrap()).unwrap();
+
+        assert_eq!(restored.data(), 20.0);
+        assert_eq!(restored.internal.borrow().previous[0].internal.borrow().operation, None);
+
+        // the form written before no_grad results were stored as leaves
+        let json = r#"[{"data":4.0,"gradient":0.0,"requires_grad":true,"label":null,"operation":"Mul","previous":[]}]"#;
+        let restored: Value = serde_json::from_str(json).unwrap();
+
+        assert_eq!(restored.data(), 4.0);
+        assert_eq!(restored.internal.borrow().operation, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_powf_keeps_its_backward() {
+        let x = Value::from(-2.0);
+        let y = x.powf(2.0);
+
+        let restored: Value = serde_json::from_str(&serde_json::to_string(&y).unwrap()).unwrap();
+        set_detect_anomaly(true);
+        restored.backward();
+        set_detect_anomaly(false);
+
+        assert_eq!(restored.internal.borrow().operation, Some(Operation::Powf));
+        assert_eq!(restored.internal.borrow().previous[0].gradient(), -4.0);
+        assert_eq!(restored.internal.borrow().previous[1].gradient(), 0.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_rejects_forward_reference() {
+        let json = r#"[{"data":1.0,"gradient":0.0,"requires_grad":true,"label":null,"operation":"Neg","previous":[0]}]"#;
+
+        assert!(serde_json::from_str::<Value>(json).is_err());
+    }
+
+    #[cfg(feature = "threadsafe")]
+    #[test]
+    fn test_threadsafe_shared_forward() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Value>();
+
+        let x = Value::from(3.0);
+
+        let (square, cube) = std::thread::scope(|scope| {
+            let square = scope.spawn(|| &x * &x);
+            let cube = scope.spawn(|| x.powf(3.0));
+
+            (square.join().unwrap(), cube.join().unwrap())
+        });
+
+        assert_eq!(square.data(), 9.0);
+        assert_eq!(cube.data(), 27.0);
+
+        (&square + &cube).backward();
+
+        assert_eq!(x.gradient(), 6.0 + 27.0);
     }
 }
 
-type BackPropagteFn = fn(value: &Ref<ValueInternal>);
+type BackPropagteFn = fn(value: &ReadGuard<ValueInternal>);
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operation {
     Add,
     Sub,
     Mul,
+    Div,
+    Neg,
+    Pow,
+    // a power with a constant exponent, which receives no gradient
+    Powf,
+    Exp,
+    Ln,
+    Tanh,
+    Relu,
+    LeakyRelu,
+    Sigmoid,
+    Max,
+    Min,
+    Abs,
+    Sqrt,
+    Recip,
+    Exp2,
+    Sin,
+    Cos,
+    Softplus,
+    Elu,
 }
 
 #[derive(Clone)]
 pub struct ValueInternal {
     data: f64,
-    gradient: f64,
+    gradient: Option<Value>,
+    requires_grad: bool,
     label: Option<String>,
     operation: Option<Operation>,
     previous: Vec<Value>,
@@ -275,19 +2446,106 @@ impl ValueInternal {
     ) -> ValueInternal {
         ValueInternal {
             data,
-            gradient: 0.0,
+            gradient: None,
+            requires_grad: true,
             label,
             operation,
             previous,
             propagate,
         }
     }
+
+    fn gradient(&self) -> f64 {
+        self.gradient.as_ref().map_or(0.0, Value::data)
+    }
+
+    fn upstream(&self) -> Value {
+        self.gradient.clone().unwrap_or_else(|| Value::from(0.0))
+    }
+}
+
+// applies operation afresh to the given operands, giving a new node; None when
+// the number of operands doesn't fit the operation
+fn replay(operation: &Operation, previous: &[Value]) -> Option<Value> {
+    let replayed = match (operation, previous) {
+        (Operation::Add, [u, v]) => add(u, v),
+        (Operation::Sub, [u, v]) => sub(u, v),
+        (Operation::Mul, [u, v]) => mul(u, v),
+        (Operation::Div, [u, v]) => div(u, v),
+        (Operation::Neg, [u]) => neg(u),
+        (Operation::Pow, [u, v]) => u.powv(v),
+        (Operation::Powf, [u, n]) => u.powf(n.data()),
+        (Operation::Exp, [u]) => u.exp(),
+        (Operation::Ln, [u]) => u.ln(),
+        (Operation::Tanh, [u]) => u.tanh(),
+        (Operation::Relu, [u]) => u.relu(),
+        (Operation::LeakyRelu, [u, slope]) => u.leaky_relu(slope.data()),
+        (Operation::Sigmoid, [u]) => u.sigmoid(),
+        (Operation::Max, [u, v]) => u.max(v),
+        (Operation::Min, [u, v]) => u.min(v),
+        (Operation::Abs, [u]) => u.abs(),
+        (Operation::Sqrt, [u]) => u.sqrt(),
+        (Operation::Recip, [u]) => u.recip(),
+        (Operation::Exp2, [u]) => u.exp2(),
+        (Operation::Sin, [u]) => u.sin(),
+        (Operation::Cos, [u]) => u.cos(),
+        (Operation::Softplus, [u]) => u.softplus(),
+        (Operation::Elu, [u, alpha]) => u.elu(alpha.data()),
+        _ => return None,
+    };
+
+    Some(replayed)
+}
+
+fn check_anomaly(internal: &ValueInternal, kind: &str, x: f64) {
+    if x.is_finite() {
+        return;
+    }
+
+    let operation = match &internal.operation {
+        Some(operation) => format!("{:?}", operation),
+        None => "leaf".to_string(),
+    };
+    panic!(
+        "anomaly detected: {} node (label {:?}) produced non-finite {} {}",
+        operation, internal.label, kind, x
+    );
+}
+
+// adds contribution to the gradient of value; both are nodes, so when the graph
+// is being recorded the accumulated gradient can itself be differentiated
+fn accumulate(value: &Value, contribution: Value) {
+    if !value.internal.borrow().requires_grad {
+        return;
+    }
+
+    let gradient = value.internal.borrow().gradient.clone();
+    let gradient = match gradient {
+        Some(gradient) => add(&gradient, &contribution),
+        None => contribution,
+    };
+
+    value.internal.borrow_mut().gradient = Some(gradient);
+}
+
+// dropping a long chain would otherwise recurse once per node, so the
+// previous links of uniquely owned ancestors are unwound iteratively
+impl Drop for ValueInternal {
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.previous);
+
+        while let Some(value) = stack.pop() {
+            if let Ok(internal) = Shared::try_unwrap(value.internal) {
+                stack.append(&mut internal.borrow_mut().previous);
+            }
+        }
+    }
 }
 
 impl PartialEq for ValueInternal {
     fn eq(&self, other: &Self) -> bool {
         self.data == other.data
-            && self.gradient == other.gradient
+            && self.gradient() == other.gradient()
             && self.label == other.label
             && self.operation == other.operation
             && self.previous == other.previous
@@ -299,7 +2557,7 @@ impl Eq for ValueInternal {}
 impl Hash for ValueInternal {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.data.to_bits().hash(state);
-        self.gradient.to_bits().hash(state);
+        self.gradient().to_bits().hash(state);
         self.label.hash(state);
         self.operation.hash(state);
         self.previous.hash(state);
@@ -311,7 +2569,7 @@ impl Debug for ValueInternal {
         write!(
             f,
             "ValueInternal {{ data: {}, gradient: {}, label: {:?}, operation: {:?}, previous: {:?} }}",
-            self.data, self.gradient, self.label, self.operation, self.previous
+            self.data, self.gradient(), self.label, self.operation, self.previous
         )
     }
 }
\ No newline at end of file
@@ -0,0 +1,7 @@
+#[path = "../examples/regression.rs"]
+mod regression;
+
+#[test]
+fn test_regression_example() {
+    regression::main();
+}
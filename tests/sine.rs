@@ -0,0 +1,7 @@
+#[path = "../examples/sine.rs"]
+mod sine;
+
+#[test]
+fn test_sine_example() {
+    sine::main();
+}